@@ -0,0 +1,24 @@
+//! Client for [Archipelago](https://archipelago.gg) multiworld servers.
+
+pub use client::Client;
+pub use session::{Event, Session};
+
+mod client;
+#[cfg(test)]
+mod mock_server;
+pub mod protocol;
+mod session;
+
+/// Default port of Archipelago servers.
+pub const DEFAULT_PORT: u16 = 38281;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConnectSettings {
+    /// Server address, e.g. `archipelago.gg:38281` or `ws://localhost:38281`.
+    pub server: String,
+    /// Slot (player) name.
+    pub slot: String,
+    pub password: String,
+    /// Game of the slot. May be left empty because the tracker connects with the `Tracker` tag.
+    pub game: String,
+}
@@ -0,0 +1,179 @@
+use std::io;
+use std::net::TcpStream;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+use eyre::{bail, eyre, Context, Result};
+use tracing::{debug, error, info, instrument, warn};
+use tungstenite::http::Uri;
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Message, WebSocket};
+
+use crate::archipelago::protocol::{ClientPacket, ServerPacket};
+use crate::archipelago::{ConnectSettings, Event, Session, DEFAULT_PORT};
+
+/// How long to wait for server messages before checking for outgoing packets.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+type Socket = WebSocket<MaybeTlsStream<TcpStream>>;
+
+/// Connection to an Archipelago server.
+///
+/// The connection is handled by a background thread and closed when the client is dropped.
+pub struct Client {
+    events: Receiver<Event>,
+    packets: Sender<ClientPacket>,
+}
+
+impl Client {
+    pub fn connect(settings: ConnectSettings) -> Self {
+        let (event_sender, events) = mpsc::channel();
+        let (packets, packet_receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            let error = run(settings, &event_sender, &packet_receiver)
+                .inspect_err(|err| error!("archipelago connection failed: {err:?}"))
+                .err()
+                .map(|err| format!("{err:#}"));
+
+            event_sender.send(Event::Disconnected { error }).ok();
+        });
+
+        Self { events, packets }
+    }
+
+    /// Returns all events received since the last poll.
+    pub fn poll(&self) -> Vec<Event> {
+        self.events.try_iter().collect()
+    }
+
+    /// Sends a packet to the server.
+    pub fn send(&self, packet: ClientPacket) {
+        if self.packets.send(packet).is_err() {
+            warn!("can't send packet, not connected");
+        }
+    }
+}
+
+#[instrument(skip_all, fields(server = settings.server))]
+fn run(
+    settings: ConnectSettings,
+    events: &Sender<Event>,
+    packets: &Receiver<ClientPacket>,
+) -> Result<()> {
+    let mut socket = connect(&settings.server)?;
+    let mut session = Session::new(settings);
+
+    info!("connected");
+
+    loop {
+        match socket.read() {
+            Ok(Message::Text(text)) => {
+                let mut replies = Vec::new();
+                let mut new_events = Vec::new();
+
+                match serde_json::from_str::<Vec<ServerPacket>>(&text) {
+                    Ok(server_packets) => {
+                        for packet in server_packets {
+                            session.handle(packet, &mut replies, &mut new_events);
+                        }
+                    }
+                    Err(err) => error!("failed to parse server message: {err:?}"),
+                }
+
+                send(&mut socket, &replies)?;
+
+                for event in new_events {
+                    if events.send(event).is_err() {
+                        return Ok(());
+                    }
+                }
+            }
+            Ok(Message::Close(_)) => {
+                debug!("server closed the connection");
+                return Ok(());
+            }
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(err))
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) => {}
+            Err(err) => return Err(err).context("failed to read from server"),
+        }
+
+        let mut outgoing = Vec::new();
+
+        loop {
+            match packets.try_recv() {
+                Ok(packet) => outgoing.push(packet),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    debug!("client dropped, closing connection");
+                    socket.close(None).ok();
+                    socket.flush().ok();
+                    return Ok(());
+                }
+            }
+        }
+
+        send(&mut socket, &outgoing)?;
+    }
+}
+
+fn send(socket: &mut Socket, packets: &[ClientPacket]) -> Result<()> {
+    if packets.is_empty() {
+        return Ok(());
+    }
+
+    let message = serde_json::to_string(packets).context("failed to serialize packets")?;
+
+    socket
+        .send(Message::text(message))
+        .context("failed to send packets")
+}
+
+/// Connects to the server. Without an explicit scheme a secure connection is tried first.
+fn connect(server: &str) -> Result<Socket> {
+    if server.contains("://") {
+        return connect_url(server);
+    }
+
+    connect_url(&format!("wss://{server}")).or_else(|err| {
+        debug!("secure connection failed, trying insecure connection: {err:?}");
+        connect_url(&format!("ws://{server}"))
+    })
+}
+
+fn connect_url(url: &str) -> Result<Socket> {
+    let uri = url
+        .parse::<Uri>()
+        .with_context(|| eyre!("invalid server address `{url}`"))?;
+    let Some(host) = uri.host() else {
+        bail!("server address `{url}` has no host");
+    };
+    let port = uri.port_u16().unwrap_or(DEFAULT_PORT);
+    let url = format!(
+        "{}://{host}:{port}{}",
+        uri.scheme_str().unwrap_or("ws"),
+        uri.path()
+    );
+
+    let stream = TcpStream::connect((host, port))
+        .with_context(|| eyre!("failed to connect to {host}:{port}"))?;
+    let (socket, _response) = tungstenite::client_tls(url.as_str(), stream)
+        .map_err(|err| eyre!("websocket handshake with {url} failed: {err}"))?;
+
+    let stream = match socket.get_ref() {
+        MaybeTlsStream::Plain(stream) => stream,
+        MaybeTlsStream::Rustls(stream) => stream.get_ref(),
+        _ => bail!("unsupported stream type"),
+    };
+
+    stream
+        .set_read_timeout(Some(POLL_INTERVAL))
+        .context("failed to set read timeout")?;
+
+    Ok(socket)
+}
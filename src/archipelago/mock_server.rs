@@ -0,0 +1,502 @@
+//! In-process stand-in for an Archipelago server, used to test the client end-to-end.
+
+use std::collections::HashSet;
+use std::io;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use eyre::{Context, Result};
+use parking_lot::Mutex;
+use serde_json::{Map, Value};
+use tungstenite::{Message, WebSocket};
+
+use crate::archipelago::protocol::{
+    ClientPacket, Connected, DataPackage, DataPackageData, Id, NetworkItem, ReceivedItems,
+    Retrieved, RoomInfo, RoomUpdate, ServerPacket, SetReply,
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Everything the mock server tells a connecting client.
+#[derive(Debug, Clone, Default)]
+pub struct Room {
+    pub connected: Connected,
+    pub data_package: DataPackageData,
+    pub items: Vec<NetworkItem>,
+    pub data_storage: Map<String, Value>,
+}
+
+pub struct MockServer {
+    address: SocketAddr,
+    state: Arc<Mutex<State>>,
+    packets: Sender<Vec<ServerPacket>>,
+    stop: Arc<AtomicBool>,
+}
+
+#[derive(Default)]
+struct State {
+    room: Room,
+    notify_keys: HashSet<String>,
+    received_packets: Vec<ClientPacket>,
+    /// Why the server thread stopped, if it failed.
+    error: Option<eyre::Report>,
+}
+
+impl MockServer {
+    pub fn start(room: Room) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("failed to bind mock server");
+        let address = listener.local_addr().unwrap();
+        let state = Arc::new(Mutex::new(State {
+            room,
+            ..State::default()
+        }));
+        let (packets, packet_receiver) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+
+        listener.set_nonblocking(true).unwrap();
+
+        thread::spawn({
+            let state = state.clone();
+            let stop = stop.clone();
+
+            move || {
+                if let Err(err) = serve(listener, &state, &packet_receiver, &stop) {
+                    state.lock().error = Some(err);
+                }
+            }
+        });
+
+        Self {
+            address,
+            state,
+            packets,
+            stop,
+        }
+    }
+
+    pub fn url(&self) -> String {
+        format!("ws://{}", self.address)
+    }
+
+    /// Packets received from clients so far.
+    pub fn received_packets(&self) -> Vec<ClientPacket> {
+        self.state.lock().received_packets.clone()
+    }
+
+    /// Takes the error the server thread stopped with, if any.
+    pub fn take_error(&self) -> Option<eyre::Report> {
+        self.state.lock().error.take()
+    }
+
+    pub fn send_items(&self, items: &[NetworkItem]) {
+        let mut state = self.state.lock();
+        let index = state.room.items.len();
+
+        state.room.items.extend_from_slice(items);
+
+        self.send(ServerPacket::ReceivedItems(ReceivedItems {
+            index,
+            items: items.to_vec(),
+        }));
+    }
+
+    pub fn check_locations(&self, locations: &[Id]) {
+        self.send(ServerPacket::RoomUpdate(RoomUpdate {
+            checked_locations: locations.to_vec(),
+        }));
+    }
+
+    /// Changes a data storage value and notifies subscribed clients.
+    pub fn set(&self, key: &str, value: Value) {
+        let mut state = self.state.lock();
+        let original_value = state
+            .room
+            .data_storage
+            .insert(key.to_owned(), value.clone())
+            .unwrap_or_default();
+
+        if state.notify_keys.contains(key) {
+            self.send(ServerPacket::SetReply(SetReply {
+                key: key.to_owned(),
+                value,
+                original_value,
+            }));
+        }
+    }
+
+    fn send(&self, packet: ServerPacket) {
+        self.packets.send(vec![packet]).unwrap();
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+fn serve(
+    listener: TcpListener,
+    state: &Mutex<State>,
+    packets: &Receiver<Vec<ServerPacket>>,
+    stop: &AtomicBool,
+) -> Result<()> {
+    while !stop.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, _)) => serve_client(stream, state, packets, stop)
+                .context("mock server connection failed")?,
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+            Err(err) => return Err(err).context("mock server failed to accept"),
+        }
+    }
+
+    Ok(())
+}
+
+fn serve_client(
+    stream: TcpStream,
+    state: &Mutex<State>,
+    packets: &Receiver<Vec<ServerPacket>>,
+    stop: &AtomicBool,
+) -> Result<()> {
+    stream.set_nonblocking(false)?;
+    let mut socket = tungstenite::accept(stream).map_err(|err| match err {
+        tungstenite::HandshakeError::Failure(err) => err,
+        tungstenite::HandshakeError::Interrupted(_) => tungstenite::Error::ConnectionClosed,
+    })?;
+    socket.get_ref().set_read_timeout(Some(POLL_INTERVAL))?;
+
+    let room_info = ServerPacket::RoomInfo(RoomInfo {
+        password: false,
+        games: vec![],
+        seed_name: "mock".into(),
+    });
+    send(&mut socket, &[room_info])?;
+
+    while !stop.load(Ordering::Relaxed) {
+        match socket.read() {
+            Ok(Message::Text(text)) => {
+                let client_packets = serde_json::from_str::<Vec<ClientPacket>>(&text)
+                    .context("client sent invalid packets")?;
+                let mut replies = Vec::new();
+
+                for packet in client_packets {
+                    handle(&mut state.lock(), packet, &mut replies);
+                }
+
+                send(&mut socket, &replies)?;
+            }
+            Ok(Message::Close(_)) | Err(tungstenite::Error::ConnectionClosed) => return Ok(()),
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(err))
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) => {}
+            Err(err) => return Err(err.into()),
+        }
+
+        for server_packets in packets.try_iter() {
+            send(&mut socket, &server_packets)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn handle(state: &mut State, packet: ClientPacket, replies: &mut Vec<ServerPacket>) {
+    state.received_packets.push(packet.clone());
+
+    match packet {
+        ClientPacket::GetDataPackage(_) => replies.push(ServerPacket::DataPackage(DataPackage {
+            data: state.room.data_package.clone(),
+        })),
+        ClientPacket::Connect(_) => {
+            replies.push(ServerPacket::Connected(state.room.connected.clone()));
+            replies.push(received_items(state));
+        }
+        ClientPacket::Sync => replies.push(received_items(state)),
+        ClientPacket::Get(get) => {
+            let keys = get
+                .keys
+                .into_iter()
+                .map(|key| {
+                    let value = state.room.data_storage.get(&key).cloned();
+                    (key, value.unwrap_or_default())
+                })
+                .collect();
+
+            replies.push(ServerPacket::Retrieved(Retrieved { keys }));
+        }
+        ClientPacket::SetNotify(set_notify) => state.notify_keys.extend(set_notify.keys),
+    }
+}
+
+fn received_items(state: &State) -> ServerPacket {
+    ServerPacket::ReceivedItems(ReceivedItems {
+        index: 0,
+        items: state.room.items.clone(),
+    })
+}
+
+fn send(socket: &mut WebSocket<TcpStream>, packets: &[ServerPacket]) -> Result<()> {
+    if packets.is_empty() {
+        return Ok(());
+    }
+
+    let message = serde_json::to_string(packets).context("failed to serialize packets")?;
+
+    socket
+        .send(Message::text(message))
+        .context("failed to send packets")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use crate::archipelago::protocol::NetworkSlot;
+    use crate::archipelago::{Client, ConnectSettings, Event};
+    use crate::pack::test_helpers::{eval, fixture_pack};
+    use crate::pack::Pack;
+
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    fn room() -> Room {
+        Room {
+            connected: Connected {
+                team: 0,
+                slot: 1,
+                checked_locations: vec![10],
+                missing_locations: vec![11],
+                slot_data: json!({ "goal": "ganon" }),
+                slot_info: [(
+                    "1".into(),
+                    NetworkSlot {
+                        name: "Link".into(),
+                        game: "Test Game".into(),
+                    },
+                )]
+                .into(),
+                ..Connected::default()
+            },
+            data_package: serde_json::from_value(json!({
+                "games": {
+                    "Test Game": {
+                        "item_name_to_id": { "Sword": 1, "Lamp": 2 },
+                        "location_name_to_id": { "Cave": 10, "Tower": 11 },
+                    },
+                },
+            }))
+            .unwrap(),
+            items: vec![network_item(1)],
+            data_storage: Map::new(),
+        }
+    }
+
+    fn network_item(item: Id) -> NetworkItem {
+        NetworkItem {
+            item,
+            location: 10,
+            player: 1,
+            flags: 0,
+        }
+    }
+
+    fn connect(server: &MockServer) -> Client {
+        Client::connect(ConnectSettings {
+            server: server.url(),
+            slot: "Link".into(),
+            ..ConnectSettings::default()
+        })
+    }
+
+    /// Dispatches client events to the pack and sends the packets requested by the pack
+    /// until an event matches. Fails as soon as the mock server does.
+    fn pump_until(
+        server: &MockServer,
+        client: &Client,
+        pack: &Pack,
+        done: impl Fn(&Event) -> bool,
+    ) {
+        let deadline = Instant::now() + TIMEOUT;
+
+        loop {
+            if let Some(err) = server.take_error() {
+                panic!("{err:?}");
+            }
+
+            let events = client.poll();
+
+            for event in &events {
+                assert!(
+                    !matches!(event, Event::Disconnected { .. }),
+                    "unexpected disconnect: {event:?}"
+                );
+                pack.api.handle_archipelago_event(event).unwrap();
+            }
+
+            for packet in pack.api.take_archipelago_packets().unwrap() {
+                client.send(packet);
+            }
+
+            if events.iter().any(&done) {
+                return;
+            }
+
+            assert!(Instant::now() < deadline, "timed out waiting for event");
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Provider counts of the sword and lamp.
+    fn provider_counts(pack: &Pack) -> [i32; 2] {
+        pack.api
+            .with_tracker(|tracker| {
+                ["sword", "lamp"].map(|code| tracker.provider_count_for_item(code))
+            })
+            .unwrap()
+    }
+
+    fn item_received(index: usize) -> impl Fn(&Event) -> bool {
+        move |event| matches!(event, Event::ItemReceived { index: i, .. } if *i == index)
+    }
+
+    #[test]
+    fn connect_with_tracker_tag() {
+        let server = MockServer::start(room());
+        let pack = fixture_pack("archipelago");
+        let client = connect(&server);
+
+        pump_until(&server, &client, &pack, |event| {
+            matches!(event, Event::Connected { .. })
+        });
+
+        let connect = server
+            .received_packets()
+            .into_iter()
+            .find_map(|packet| match packet {
+                ClientPacket::Connect(connect) => Some(connect),
+                _ => None,
+            })
+            .unwrap();
+
+        assert_eq!(connect.name, "Link");
+        assert_eq!(connect.tags, ["Tracker"]);
+    }
+
+    #[test]
+    fn handlers_receive_initial_state() {
+        let server = MockServer::start(room());
+        let pack = fixture_pack("archipelago");
+        let client = connect(&server);
+
+        pump_until(&server, &client, &pack, item_received(0));
+
+        assert_eq!(eval::<String>(&pack, "return slot_data.goal"), "ganon");
+        assert_eq!(
+            eval::<Vec<String>>(&pack, "return received_items"),
+            ["Sword"]
+        );
+        assert_eq!(
+            eval::<Vec<String>>(&pack, "return checked_locations"),
+            ["Cave"]
+        );
+        assert_eq!(
+            eval::<Vec<usize>>(&pack, "return locations_on_clear"),
+            [1, 1]
+        );
+        assert_eq!(provider_counts(&pack), [1, 0]);
+    }
+
+    #[test]
+    fn handlers_receive_updates() {
+        let server = MockServer::start(room());
+        let pack = fixture_pack("archipelago");
+        let client = connect(&server);
+
+        pump_until(&server, &client, &pack, item_received(0));
+
+        server.send_items(&[network_item(2)]);
+        pump_until(&server, &client, &pack, item_received(1));
+
+        server.check_locations(&[11]);
+        pump_until(&server, &client, &pack, |event| {
+            matches!(event, Event::LocationChecked { location: 11, .. })
+        });
+
+        assert_eq!(
+            eval::<Vec<String>>(&pack, "return received_items"),
+            ["Sword", "Lamp"]
+        );
+        assert_eq!(
+            eval::<Vec<String>>(&pack, "return checked_locations"),
+            ["Cave", "Tower"]
+        );
+        assert_eq!(provider_counts(&pack), [1, 1]);
+    }
+
+    #[test]
+    fn connection_fields() {
+        let server = MockServer::start(room());
+        let pack = fixture_pack("archipelago");
+
+        assert_eq!(eval::<i64>(&pack, "return Archipelago.PlayerNumber"), -1);
+
+        let client = connect(&server);
+
+        pump_until(&server, &client, &pack, item_received(0));
+
+        server.check_locations(&[11]);
+        pump_until(&server, &client, &pack, |event| {
+            matches!(event, Event::LocationChecked { location: 11, .. })
+        });
+
+        assert_eq!(eval::<i64>(&pack, "return Archipelago.PlayerNumber"), 1);
+        assert_eq!(eval::<i64>(&pack, "return Archipelago.TeamNumber"), 0);
+        assert_eq!(
+            eval::<Vec<Id>>(&pack, "return Archipelago.CheckedLocations"),
+            [10, 11]
+        );
+        assert_eq!(
+            eval::<Vec<Id>>(&pack, "return Archipelago.MissingLocations"),
+            Vec::<Id>::new()
+        );
+    }
+
+    #[test]
+    fn data_storage_replies() {
+        let mut room = room();
+        room.data_storage.insert("area".into(), json!("Hyrule"));
+
+        let server = MockServer::start(room);
+        let pack = fixture_pack("archipelago");
+        let client = connect(&server);
+
+        // The pack subscribes to `area` and requests its value when connected.
+        pump_until(&server, &client, &pack, |event| {
+            matches!(event, Event::Retrieved { .. })
+        });
+
+        assert_eq!(eval::<String>(&pack, "return retrieved.area"), "Hyrule");
+
+        server.set("area", json!("Dark World"));
+        pump_until(&server, &client, &pack, |event| {
+            matches!(event, Event::SetReply { .. })
+        });
+
+        assert_eq!(
+            eval::<String>(&pack, "return set_replies.area"),
+            "Dark World"
+        );
+    }
+}
@@ -0,0 +1,252 @@
+//! Packets of the [Archipelago network protocol](https://github.com/ArchipelagoMW/Archipelago/blob/main/docs/network%20protocol.md).
+//!
+//! Only the commands and fields used by the tracker are modelled, everything else is ignored.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Id of an item or location.
+pub type Id = i64;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "cmd")]
+pub enum ServerPacket {
+    RoomInfo(RoomInfo),
+    ConnectionRefused(ConnectionRefused),
+    Connected(Connected),
+    ReceivedItems(ReceivedItems),
+    RoomUpdate(RoomUpdate),
+    DataPackage(DataPackage),
+    Retrieved(Retrieved),
+    SetReply(SetReply),
+    /// Commands the tracker doesn't care about, like `PrintJSON` or `Bounced`.
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct RoomInfo {
+    #[serde(default)]
+    pub password: bool,
+    #[serde(default)]
+    pub games: Vec<String>,
+    #[serde(default)]
+    pub seed_name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct ConnectionRefused {
+    #[serde(default)]
+    pub errors: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Connected {
+    pub team: i64,
+    pub slot: i64,
+    #[serde(default)]
+    pub players: Vec<NetworkPlayer>,
+    #[serde(default)]
+    pub missing_locations: Vec<Id>,
+    #[serde(default)]
+    pub checked_locations: Vec<Id>,
+    #[serde(default)]
+    pub slot_data: Value,
+    /// Slots keyed by slot number.
+    #[serde(default)]
+    pub slot_info: HashMap<String, NetworkSlot>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct ReceivedItems {
+    pub index: usize,
+    pub items: Vec<NetworkItem>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct RoomUpdate {
+    #[serde(default)]
+    pub checked_locations: Vec<Id>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct DataPackage {
+    pub data: DataPackageData,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct DataPackageData {
+    #[serde(default)]
+    pub games: HashMap<String, GameData>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct GameData {
+    #[serde(default)]
+    pub item_name_to_id: HashMap<String, Id>,
+    #[serde(default)]
+    pub location_name_to_id: HashMap<String, Id>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Retrieved {
+    pub keys: Map<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct SetReply {
+    pub key: String,
+    pub value: Value,
+    #[serde(default)]
+    pub original_value: Value,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct NetworkPlayer {
+    pub team: i64,
+    pub slot: i64,
+    #[serde(default)]
+    pub alias: String,
+    #[serde(default)]
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct NetworkSlot {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub game: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NetworkItem {
+    pub item: Id,
+    pub location: Id,
+    pub player: i64,
+    #[serde(default)]
+    pub flags: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "cmd")]
+pub enum ClientPacket {
+    Connect(Connect),
+    GetDataPackage(GetDataPackage),
+    Sync,
+    Get(Get),
+    SetNotify(SetNotify),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Connect {
+    pub password: String,
+    pub game: String,
+    pub name: String,
+    pub uuid: String,
+    pub version: Version,
+    pub items_handling: i32,
+    pub tags: Vec<String>,
+    pub slot_data: bool,
+}
+
+impl Connect {
+    /// Receive items from other worlds, from the own world and starting inventory.
+    pub const ALL_ITEMS: i32 = 0b111;
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct GetDataPackage {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub games: Option<Vec<String>>,
+}
+
+/// Requests the values of data storage keys, answered with [`Retrieved`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Get {
+    pub keys: Vec<String>,
+}
+
+/// Subscribes to changes of data storage keys, reported with [`SetReply`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct SetNotify {
+    pub keys: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub build: u32,
+    pub class: String,
+}
+
+impl Version {
+    pub fn new(major: u32, minor: u32, build: u32) -> Self {
+        Self {
+            major,
+            minor,
+            build,
+            class: "Version".into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn deserialize_server_packets() {
+        let packets = serde_json::from_value::<Vec<ServerPacket>>(json!([
+            { "cmd": "RoomInfo", "password": false, "games": ["A Link to the Past"], "tags": [] },
+            { "cmd": "PrintJSON", "data": [] },
+            {
+                "cmd": "ReceivedItems",
+                "index": 0,
+                "items": [{ "item": 1, "location": 2, "player": 3, "flags": 0, "class": "NetworkItem" }],
+            },
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            packets,
+            [
+                ServerPacket::RoomInfo(RoomInfo {
+                    password: false,
+                    games: vec!["A Link to the Past".into()],
+                    seed_name: String::new(),
+                }),
+                ServerPacket::Unknown,
+                ServerPacket::ReceivedItems(ReceivedItems {
+                    index: 0,
+                    items: vec![NetworkItem {
+                        item: 1,
+                        location: 2,
+                        player: 3,
+                        flags: 0,
+                    }],
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn serialize_client_packets() {
+        let packets = serde_json::to_value([
+            ClientPacket::GetDataPackage(GetDataPackage::default()),
+            ClientPacket::Sync,
+        ])
+        .unwrap();
+
+        assert_eq!(
+            packets,
+            json!([{ "cmd": "GetDataPackage" }, { "cmd": "Sync" }])
+        );
+    }
+}
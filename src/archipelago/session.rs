@@ -0,0 +1,457 @@
+use std::collections::{HashMap, HashSet};
+
+use serde_json::{Map, Value};
+use tracing::{debug, warn};
+
+use crate::archipelago::protocol::{
+    self, ClientPacket, Connect, GetDataPackage, Id, ServerPacket, Version,
+};
+use crate::archipelago::ConnectSettings;
+
+/// Protocol version the client claims to speak.
+pub const PROTOCOL_VERSION: (u32, u32, u32) = (0, 5, 0);
+
+/// Something the tracker needs to react to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// Slot connection established. Tracker state should be reset.
+    Connected {
+        team: i64,
+        slot: i64,
+        slot_data: Value,
+        checked_locations: Vec<Id>,
+        missing_locations: Vec<Id>,
+    },
+    Refused {
+        errors: Vec<String>,
+    },
+    ItemReceived {
+        index: usize,
+        item: Id,
+        item_name: String,
+        player: i64,
+    },
+    LocationChecked {
+        location: Id,
+        location_name: String,
+    },
+    Retrieved {
+        keys: Map<String, Value>,
+    },
+    SetReply {
+        key: String,
+        value: Value,
+        original_value: Value,
+    },
+    /// The connection was closed, with an error message if it wasn't closed cleanly.
+    Disconnected {
+        error: Option<String>,
+    },
+}
+
+/// Protocol state of a connection to an Archipelago server.
+///
+/// Turns server packets into replies for the server and [`Event`]s for the tracker.
+/// It does no IO itself, so it can be driven by any transport.
+#[derive(Debug)]
+pub struct Session {
+    settings: ConnectSettings,
+    games: HashMap<String, GameNames>,
+    game: String,
+    next_item_index: usize,
+    checked_locations: HashSet<Id>,
+}
+
+#[derive(Debug, Default)]
+struct GameNames {
+    items: HashMap<Id, String>,
+    locations: HashMap<Id, String>,
+}
+
+impl Session {
+    pub fn new(settings: ConnectSettings) -> Self {
+        let game = settings.game.clone();
+
+        Self {
+            settings,
+            games: HashMap::new(),
+            game,
+            next_item_index: 0,
+            checked_locations: HashSet::new(),
+        }
+    }
+
+    pub fn handle(
+        &mut self,
+        packet: ServerPacket,
+        replies: &mut Vec<ClientPacket>,
+        events: &mut Vec<Event>,
+    ) {
+        match packet {
+            ServerPacket::RoomInfo(room_info) => {
+                debug!(seed = room_info.seed_name, "received room info");
+
+                replies.push(ClientPacket::GetDataPackage(GetDataPackage {
+                    games: Some(room_info.games),
+                }));
+                replies.push(ClientPacket::Connect(self.connect_packet()));
+            }
+            ServerPacket::ConnectionRefused(refused) => {
+                events.push(Event::Refused {
+                    errors: refused.errors,
+                });
+            }
+            ServerPacket::DataPackage(data_package) => {
+                for (game, data) in data_package.data.games {
+                    let names = GameNames {
+                        items: invert(data.item_name_to_id),
+                        locations: invert(data.location_name_to_id),
+                    };
+
+                    self.games.insert(game, names);
+                }
+            }
+            ServerPacket::Connected(connected) => self.handle_connected(connected, events),
+            ServerPacket::ReceivedItems(received_items) => {
+                self.handle_received_items(received_items, replies, events)
+            }
+            ServerPacket::RoomUpdate(room_update) => {
+                self.check_locations(room_update.checked_locations, events);
+            }
+            ServerPacket::Retrieved(retrieved) => {
+                events.push(Event::Retrieved {
+                    keys: retrieved.keys,
+                });
+            }
+            ServerPacket::SetReply(set_reply) => {
+                events.push(Event::SetReply {
+                    key: set_reply.key,
+                    value: set_reply.value,
+                    original_value: set_reply.original_value,
+                });
+            }
+            ServerPacket::Unknown => {}
+        }
+    }
+
+    fn connect_packet(&self) -> Connect {
+        let (major, minor, build) = PROTOCOL_VERSION;
+
+        Connect {
+            password: self.settings.password.clone(),
+            game: self.settings.game.clone(),
+            name: self.settings.slot.clone(),
+            uuid: env!("CARGO_PKG_NAME").into(),
+            version: Version::new(major, minor, build),
+            items_handling: Connect::ALL_ITEMS,
+            tags: vec!["Tracker".into()],
+            slot_data: true,
+        }
+    }
+
+    fn handle_connected(&mut self, connected: protocol::Connected, events: &mut Vec<Event>) {
+        if let Some(slot) = connected.slot_info.get(&connected.slot.to_string()) {
+            self.game = slot.game.clone();
+        }
+
+        self.next_item_index = 0;
+        self.checked_locations.clear();
+
+        events.push(Event::Connected {
+            team: connected.team,
+            slot: connected.slot,
+            slot_data: connected.slot_data,
+            checked_locations: connected.checked_locations.clone(),
+            missing_locations: connected.missing_locations,
+        });
+
+        self.check_locations(connected.checked_locations, events);
+    }
+
+    fn handle_received_items(
+        &mut self,
+        received_items: protocol::ReceivedItems,
+        replies: &mut Vec<ClientPacket>,
+        events: &mut Vec<Event>,
+    ) {
+        let protocol::ReceivedItems { index, items } = received_items;
+
+        if index > self.next_item_index {
+            warn!(
+                "missed items (got index {index}, expected {}), resyncing",
+                self.next_item_index
+            );
+            replies.push(ClientPacket::Sync);
+            return;
+        }
+
+        for (index, item) in (index..).zip(items) {
+            // Items are resent on sync, skip the ones that were handled already.
+            if index < self.next_item_index {
+                continue;
+            }
+
+            events.push(Event::ItemReceived {
+                index,
+                item: item.item,
+                item_name: self.item_name(item.item),
+                player: item.player,
+            });
+
+            self.next_item_index = index + 1;
+        }
+    }
+
+    fn check_locations(&mut self, locations: Vec<Id>, events: &mut Vec<Event>) {
+        for location in locations {
+            if !self.checked_locations.insert(location) {
+                continue;
+            }
+
+            events.push(Event::LocationChecked {
+                location,
+                location_name: self.location_name(location),
+            });
+        }
+    }
+
+    fn item_name(&self, item: Id) -> String {
+        self.games
+            .get(&self.game)
+            .and_then(|names| names.items.get(&item))
+            .cloned()
+            .unwrap_or_else(|| format!("Unknown item {item}"))
+    }
+
+    fn location_name(&self, location: Id) -> String {
+        self.games
+            .get(&self.game)
+            .and_then(|names| names.locations.get(&location))
+            .cloned()
+            .unwrap_or_else(|| format!("Unknown location {location}"))
+    }
+}
+
+fn invert(name_to_id: HashMap<String, Id>) -> HashMap<Id, String> {
+    name_to_id
+        .into_iter()
+        .map(|(name, id)| (id, name))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use crate::archipelago::protocol::{DataPackage, NetworkItem, NetworkSlot, ReceivedItems};
+
+    use super::*;
+
+    fn packet(packet: Value) -> ServerPacket {
+        serde_json::from_value(packet).unwrap()
+    }
+
+    fn connected_session() -> Session {
+        let mut session = Session::new(ConnectSettings {
+            slot: "Link".into(),
+            ..ConnectSettings::default()
+        });
+
+        session.handle(
+            ServerPacket::DataPackage(DataPackage {
+                data: packet_data(),
+            }),
+            &mut Vec::new(),
+            &mut Vec::new(),
+        );
+        session.handle(
+            ServerPacket::Connected(protocol::Connected {
+                team: 0,
+                slot: 1,
+                slot_info: [(
+                    "1".into(),
+                    NetworkSlot {
+                        name: "Link".into(),
+                        game: "Zelda".into(),
+                    },
+                )]
+                .into(),
+                ..protocol::Connected::default()
+            }),
+            &mut Vec::new(),
+            &mut Vec::new(),
+        );
+
+        session
+    }
+
+    fn packet_data() -> protocol::DataPackageData {
+        serde_json::from_value(json!({
+            "games": {
+                "Zelda": {
+                    "item_name_to_id": { "Sword": 1, "Lamp": 2 },
+                    "location_name_to_id": { "Cave": 10 },
+                },
+            },
+        }))
+        .unwrap()
+    }
+
+    fn received_items(index: usize, items: &[Id]) -> ServerPacket {
+        ServerPacket::ReceivedItems(ReceivedItems {
+            index,
+            items: items
+                .iter()
+                .map(|&item| NetworkItem {
+                    item,
+                    location: 10,
+                    player: 1,
+                    flags: 0,
+                })
+                .collect(),
+        })
+    }
+
+    #[test]
+    fn connects_after_room_info() {
+        let mut session = Session::new(ConnectSettings {
+            slot: "Link".into(),
+            password: "hunter2".into(),
+            ..ConnectSettings::default()
+        });
+        let mut replies = Vec::new();
+
+        session.handle(
+            packet(json!({ "cmd": "RoomInfo", "games": ["Zelda"] })),
+            &mut replies,
+            &mut Vec::new(),
+        );
+
+        let [ClientPacket::GetDataPackage(get_data_package), ClientPacket::Connect(connect)] =
+            replies.as_slice()
+        else {
+            panic!("unexpected replies: {replies:#?}");
+        };
+
+        assert_eq!(get_data_package.games, Some(vec!["Zelda".into()]));
+        assert_eq!(connect.name, "Link");
+        assert_eq!(connect.password, "hunter2");
+        assert_eq!(connect.items_handling, Connect::ALL_ITEMS);
+    }
+
+    #[test]
+    fn connected_reports_checked_locations() {
+        let mut session = Session::new(ConnectSettings::default());
+        let mut events = Vec::new();
+
+        session.handle(
+            ServerPacket::Connected(protocol::Connected {
+                checked_locations: vec![10],
+                slot_data: json!({ "goal": 1 }),
+                ..protocol::Connected::default()
+            }),
+            &mut Vec::new(),
+            &mut events,
+        );
+
+        assert_eq!(
+            events,
+            [
+                Event::Connected {
+                    team: 0,
+                    slot: 0,
+                    slot_data: json!({ "goal": 1 }),
+                    checked_locations: vec![10],
+                    missing_locations: vec![],
+                },
+                Event::LocationChecked {
+                    location: 10,
+                    location_name: "Unknown location 10".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn received_items_are_named() {
+        let mut session = connected_session();
+        let mut events = Vec::new();
+
+        session.handle(received_items(0, &[1, 2]), &mut Vec::new(), &mut events);
+
+        assert_eq!(
+            events,
+            [
+                Event::ItemReceived {
+                    index: 0,
+                    item: 1,
+                    item_name: "Sword".into(),
+                    player: 1,
+                },
+                Event::ItemReceived {
+                    index: 1,
+                    item: 2,
+                    item_name: "Lamp".into(),
+                    player: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn resent_items_are_skipped() {
+        let mut session = connected_session();
+        let mut events = Vec::new();
+
+        session.handle(received_items(0, &[1]), &mut Vec::new(), &mut Vec::new());
+        session.handle(received_items(0, &[1, 2]), &mut Vec::new(), &mut events);
+
+        assert_eq!(
+            events,
+            [Event::ItemReceived {
+                index: 1,
+                item: 2,
+                item_name: "Lamp".into(),
+                player: 1,
+            }]
+        );
+    }
+
+    #[test]
+    fn missed_items_trigger_sync() {
+        let mut session = connected_session();
+        let mut replies = Vec::new();
+        let mut events = Vec::new();
+
+        session.handle(received_items(3, &[1]), &mut replies, &mut events);
+
+        assert_eq!(replies, [ClientPacket::Sync]);
+        assert_eq!(events, Vec::<Event>::new());
+    }
+
+    #[test]
+    fn room_update_reports_new_locations_once() {
+        let mut session = connected_session();
+        let mut events = Vec::new();
+
+        session.handle(
+            packet(json!({ "cmd": "RoomUpdate", "checked_locations": [10] })),
+            &mut Vec::new(),
+            &mut events,
+        );
+        session.handle(
+            packet(json!({ "cmd": "RoomUpdate", "checked_locations": [10] })),
+            &mut Vec::new(),
+            &mut events,
+        );
+
+        assert_eq!(
+            events,
+            [Event::LocationChecked {
+                location: 10,
+                location_name: "Cave".into(),
+            }]
+        );
+    }
+}
@@ -0,0 +1,148 @@
+//! Autotracking by reading the memory of a running game.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+
+use eyre::Result;
+use parking_lot::Mutex;
+use tracing::{error, info};
+
+pub use memory::{MemoryWatches, ReadRequest, ReadResult, Segment};
+pub use variables::{VariableStore, VariableWatches};
+
+#[cfg(test)]
+mod fake_emulator;
+#[cfg(test)]
+mod fake_uat;
+#[cfg(test)]
+mod fake_usb2snes;
+pub mod lua_connector;
+pub mod memory;
+pub mod uat;
+pub mod usb2snes;
+pub mod variables;
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Status {
+    #[default]
+    Disconnected,
+    Connecting,
+    Connected {
+        device: String,
+    },
+    Error(String),
+}
+
+impl Status {
+    pub fn is_connected(&self) -> bool {
+        matches!(self, Self::Connected { .. })
+    }
+}
+
+/// Source of memory reads.
+pub trait Backend {
+    fn status(&self) -> Status;
+
+    /// Queues a read. The result is returned by a later [`poll`](Backend::poll).
+    fn request(&self, request: ReadRequest);
+
+    /// Returns the results of all reads finished since the last poll.
+    fn poll(&self) -> Vec<ReadResult>;
+}
+
+/// Device that reads memory synchronously.
+pub trait Device {
+    fn read(&mut self, address: u32, length: usize) -> Result<Vec<u8>>;
+}
+
+/// Backend driving a [`Device`] on a background thread.
+///
+/// The thread stops once the backend is dropped or the device fails.
+pub struct ThreadedBackend {
+    requests: Sender<ReadRequest>,
+    results: Receiver<ReadResult>,
+    status: Arc<Mutex<Status>>,
+    dropped: Arc<AtomicBool>,
+}
+
+impl ThreadedBackend {
+    /// Spawns the backend thread. `connect` returns the device and its display name.
+    /// Connecting may take a while, so `connect` should give up once the flag it gets is set.
+    pub fn spawn<D, F>(connect: F) -> Self
+    where
+        D: Device,
+        F: FnOnce(&AtomicBool) -> Result<(D, String)> + Send + 'static,
+    {
+        let (requests, request_receiver) = mpsc::channel::<ReadRequest>();
+        let (result_sender, results) = mpsc::channel();
+        let status = Arc::new(Mutex::new(Status::Connecting));
+        let dropped = Arc::new(AtomicBool::new(false));
+
+        thread::spawn({
+            let status = status.clone();
+            let dropped = dropped.clone();
+
+            move || {
+                let (mut device, name) = match connect(&dropped) {
+                    Ok(device) => device,
+                    Err(err) => {
+                        error!("failed to connect to device: {err:?}");
+                        *status.lock() = Status::Error(format!("{err:#}"));
+                        return;
+                    }
+                };
+
+                info!(device = name, "connected to device");
+                *status.lock() = Status::Connected { device: name };
+
+                for request in request_receiver {
+                    let data = device.read(request.address, request.length);
+                    let failed = data.is_err();
+
+                    if let Err(err) = &data {
+                        error!("failed to read memory: {err:?}");
+                        *status.lock() = Status::Error(format!("{err:#}"));
+                    }
+
+                    let result = ReadResult {
+                        request,
+                        data: data.map_err(|err| format!("{err:#}")),
+                    };
+
+                    if result_sender.send(result).is_err() || failed {
+                        break;
+                    }
+                }
+            }
+        });
+
+        Self {
+            requests,
+            results,
+            status,
+            dropped,
+        }
+    }
+}
+
+impl Drop for ThreadedBackend {
+    fn drop(&mut self) {
+        self.dropped.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Backend for ThreadedBackend {
+    fn status(&self) -> Status {
+        self.status.lock().clone()
+    }
+
+    fn request(&self, request: ReadRequest) {
+        self.requests.send(request).ok();
+    }
+
+    fn poll(&self) -> Vec<ReadResult> {
+        self.results.try_iter().collect()
+    }
+}
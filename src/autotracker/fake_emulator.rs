@@ -0,0 +1,186 @@
+//! Stand-in for an emulator running a Lua connector script.
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::Arc;
+use std::thread;
+
+use base64::prelude::{Engine, BASE64_STANDARD};
+use parking_lot::Mutex;
+
+use crate::autotracker::lua_connector::{Request, Response};
+
+pub struct FakeEmulator {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Default)]
+struct State {
+    /// Memory of the system bus. Unset bytes read as zero.
+    memory: BTreeMap<u32, u8>,
+    /// Error message to answer requests with instead of memory.
+    error: Option<String>,
+    requests: Vec<Request>,
+}
+
+impl FakeEmulator {
+    /// Connects to the tracker and answers its requests on a background thread.
+    pub fn connect(tracker_address: SocketAddr) -> Self {
+        let stream = TcpStream::connect(tracker_address).expect("failed to connect to tracker");
+        let state = Arc::new(Mutex::new(State::default()));
+
+        thread::spawn({
+            let state = state.clone();
+            let mut writer = stream.try_clone().unwrap();
+            let reader = BufReader::new(stream);
+
+            move || {
+                for line in reader.lines() {
+                    let Ok(line) = line else {
+                        return;
+                    };
+                    let request = serde_json::from_str::<Request>(&line).unwrap();
+                    let response = respond(&mut state.lock(), request);
+                    let mut message = serde_json::to_vec(&response).unwrap();
+
+                    // Terminate like some real connector scripts do.
+                    message.extend_from_slice(b"\0\n");
+
+                    if writer.write_all(&message).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+
+        Self { state }
+    }
+
+    pub fn write(&self, address: u32, bytes: &[u8]) {
+        let mut state = self.state.lock();
+
+        for (address, &byte) in (address..).zip(bytes) {
+            state.memory.insert(address, byte);
+        }
+    }
+
+    /// Makes all following reads fail with the error.
+    pub fn fail_reads(&self, error: &str) {
+        self.state.lock().error = Some(error.to_owned());
+    }
+
+    /// Requests received so far.
+    pub fn requests(&self) -> Vec<Request> {
+        self.state.lock().requests.clone()
+    }
+}
+
+fn respond(state: &mut State, request: Request) -> Response {
+    state.requests.push(request.clone());
+
+    let data = (request.address..)
+        .take(request.value)
+        .map(|address| state.memory.get(&address).copied().unwrap_or_default())
+        .collect::<Vec<_>>();
+
+    Response {
+        id: request.id,
+        message_type: request.message_type,
+        address: request.address,
+        block: BASE64_STANDARD.encode(data),
+        error: state.error.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+    use std::time::{Duration, Instant};
+
+    use pretty_assertions::assert_eq;
+
+    use crate::autotracker::lua_connector::LuaConnector;
+    use crate::autotracker::{Backend, Device, Status};
+    use crate::pack::test_helpers::{eval, fixture_pack};
+    use crate::pack::Pack;
+
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Updates the memory watches of the pack until the condition holds.
+    fn update_until(pack: &Pack, backend: &dyn Backend, done: impl Fn() -> bool) {
+        let deadline = Instant::now() + TIMEOUT;
+
+        while !done() {
+            pack.api
+                .update_memory_watches(backend, Instant::now())
+                .unwrap();
+
+            assert!(
+                Instant::now() < deadline,
+                "timed out waiting for memory watch"
+            );
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn reads_memory() {
+        let listener = LuaConnector::bind("127.0.0.1:0").unwrap();
+        let emulator = FakeEmulator::connect(listener.local_addr().unwrap());
+
+        emulator.write(0x7E_F340, &[1, 2, 3]);
+
+        let (mut connector, _name) =
+            LuaConnector::accept(&listener, &AtomicBool::new(false)).unwrap();
+
+        assert_eq!(connector.read(0x7E_F341, 2).unwrap(), [2, 3]);
+        assert_eq!(connector.read(0x7E_F340, 1).unwrap(), [1]);
+        assert_eq!(
+            emulator
+                .requests()
+                .iter()
+                .map(|request| request.id)
+                .collect::<Vec<_>>(),
+            [0, 1]
+        );
+    }
+
+    #[test]
+    fn read_errors_are_reported() {
+        let listener = LuaConnector::bind("127.0.0.1:0").unwrap();
+        let emulator = FakeEmulator::connect(listener.local_addr().unwrap());
+        let backend = LuaConnector::backend_for(listener);
+        let pack = fixture_pack("autotracking");
+
+        emulator.fail_reads("no game loaded");
+
+        update_until(
+            &pack,
+            &backend,
+            || matches!(backend.status(), Status::Error(error) if error.contains("no game loaded")),
+        );
+    }
+
+    #[test]
+    fn memory_watch_callbacks() {
+        let listener = LuaConnector::bind("127.0.0.1:0").unwrap();
+        let emulator = FakeEmulator::connect(listener.local_addr().unwrap());
+        let backend = LuaConnector::backend_for(listener);
+        let pack = fixture_pack("autotracking");
+
+        emulator.write(0x7E_F340, &[0x01, 0x02]);
+
+        update_until(&pack, &backend, || eval::<u32>(&pack, "return sword") == 1);
+
+        assert_eq!(eval::<u32>(&pack, "return shield"), 0x0201);
+
+        emulator.write(0x7E_F341, &[0x00]);
+
+        update_until(&pack, &backend, || {
+            eval::<u32>(&pack, "return shield") == 0x0001
+        });
+    }
+}
@@ -0,0 +1,235 @@
+//! In-process stand-in for a UAT server.
+
+use std::io;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use indexmap::IndexMap;
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+use tungstenite::Message;
+
+use crate::autotracker::uat::ClientCommand;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+pub struct FakeUat {
+    address: SocketAddr,
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Default)]
+struct State {
+    variables: IndexMap<String, Value>,
+    /// Every variable change, so each connection can push the ones it hasn't sent yet.
+    changes: Vec<(String, Value)>,
+}
+
+impl FakeUat {
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("failed to bind fake UAT server");
+        let address = listener.local_addr().unwrap();
+        let state = Arc::new(Mutex::new(State::default()));
+
+        thread::spawn({
+            let state = state.clone();
+
+            move || {
+                for stream in listener.incoming() {
+                    let Ok(stream) = stream else {
+                        return;
+                    };
+                    let state = state.clone();
+
+                    stream.set_read_timeout(Some(POLL_INTERVAL)).unwrap();
+
+                    thread::spawn(move || serve(stream, &state));
+                }
+            }
+        });
+
+        Self { address, state }
+    }
+
+    pub fn url(&self) -> String {
+        format!("ws://{}", self.address)
+    }
+
+    /// Sets a variable and pushes it to synced clients.
+    pub fn set(&self, name: &str, value: Value) {
+        let mut state = self.state.lock();
+
+        state.variables.insert(name.into(), value.clone());
+        state.changes.push((name.into(), value));
+    }
+}
+
+fn serve(stream: TcpStream, state: &Mutex<State>) {
+    let mut socket = tungstenite::accept(stream).unwrap();
+    let info = json!([{
+        "cmd": "Info",
+        "name": "Fake UAT",
+        "version": "1.0.0",
+        "protocol": 0,
+    }]);
+    // Number of changes sent, once the client synced.
+    let mut sent_changes = None;
+
+    if socket.send(Message::text(info.to_string())).is_err() {
+        return;
+    }
+
+    loop {
+        match socket.read() {
+            Ok(Message::Text(text)) => {
+                let commands = serde_json::from_str::<Vec<ClientCommand>>(&text).unwrap();
+                let sync = commands
+                    .iter()
+                    .any(|command| matches!(command, ClientCommand::Sync { .. }));
+
+                if sync {
+                    let state = state.lock();
+
+                    sent_changes = Some(state.changes.len());
+
+                    if socket.send(variables_message(&state.variables)).is_err() {
+                        return;
+                    }
+                }
+            }
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(err))
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) => {}
+            Err(_) => return,
+        }
+
+        let Some(sent) = &mut sent_changes else {
+            continue;
+        };
+        let state = state.lock();
+
+        if *sent < state.changes.len() {
+            let changes = state.changes[*sent..]
+                .iter()
+                .map(|(name, value)| (name, value));
+            *sent = state.changes.len();
+
+            if socket.send(variables_message(changes)).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+fn variables_message<'a>(variables: impl IntoIterator<Item = (&'a String, &'a Value)>) -> Message {
+    let commands = variables
+        .into_iter()
+        .map(|(name, value)| json!({ "cmd": "Var", "name": name, "value": value }))
+        .collect::<Vec<_>>();
+
+    Message::text(Value::from(commands).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use pretty_assertions::assert_eq;
+
+    use crate::autotracker::uat::{Uat, Variable};
+    use crate::autotracker::Status;
+    use crate::pack::test_helpers::{eval, fixture_pack};
+    use crate::pack::Pack;
+
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Updates the variable watches of the pack until the condition holds.
+    fn update_until(pack: &Pack, uat: &Uat, done: impl Fn() -> bool) {
+        let deadline = Instant::now() + TIMEOUT;
+
+        while !done() {
+            pack.api
+                .update_variable_watches(uat, Instant::now())
+                .unwrap();
+
+            assert!(
+                Instant::now() < deadline,
+                "timed out waiting for variable watch"
+            );
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    #[test]
+    fn receives_synced_and_pushed_variables() {
+        let server = FakeUat::start();
+
+        server.set("sword", json!(1));
+
+        let uat = Uat::connect(server.url());
+        let mut variables = Vec::new();
+        let deadline = Instant::now() + TIMEOUT;
+
+        while variables.is_empty() {
+            assert!(Instant::now() < deadline, "timed out waiting for sync");
+            thread::sleep(POLL_INTERVAL);
+            variables.extend(uat.poll());
+        }
+
+        assert_eq!(
+            uat.status(),
+            Status::Connected {
+                device: "Fake UAT".into()
+            }
+        );
+
+        server.set("shield", json!("mirror"));
+
+        while variables.len() < 2 {
+            assert!(Instant::now() < deadline, "timed out waiting for push");
+            thread::sleep(POLL_INTERVAL);
+            variables.extend(uat.poll());
+        }
+
+        assert_eq!(
+            variables,
+            [
+                Variable {
+                    name: "sword".into(),
+                    value: json!(1),
+                },
+                Variable {
+                    name: "shield".into(),
+                    value: json!("mirror"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn variable_watch_callbacks() {
+        let server = FakeUat::start();
+        let pack = fixture_pack("autotracking");
+        let uat = Uat::connect(server.url());
+
+        server.set("sword", json!(2));
+
+        update_until(&pack, &uat, || eval::<u32>(&pack, "return uat_sword") == 2);
+
+        assert_eq!(eval::<Vec<String>>(&pack, "return uat_changed"), ["sword"]);
+
+        server.set("shield", json!(1));
+        server.set("sword", json!(3));
+
+        update_until(&pack, &uat, || eval::<u32>(&pack, "return uat_sword") == 3);
+
+        assert_eq!(eval::<u32>(&pack, "return uat_shield"), 1);
+    }
+}
@@ -0,0 +1,174 @@
+//! In-process stand-in for a usb2snes server with a single device.
+
+use std::collections::BTreeMap;
+use std::net::{SocketAddr, TcpListener};
+use std::sync::Arc;
+use std::thread;
+
+use parking_lot::Mutex;
+use serde::Deserialize;
+use serde_json::json;
+use tungstenite::Message;
+
+/// Size of the binary messages memory is sent in, to exercise reassembly of split reads.
+const CHUNK_SIZE: usize = 3;
+
+pub struct FakeUsb2Snes {
+    address: SocketAddr,
+    /// Memory in the usb2snes address space. Unset bytes read as zero.
+    memory: Arc<Mutex<BTreeMap<u32, u8>>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Request {
+    opcode: String,
+    #[serde(default)]
+    operands: Vec<String>,
+}
+
+impl FakeUsb2Snes {
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("failed to bind fake usb2snes");
+        let address = listener.local_addr().unwrap();
+        let memory = Arc::new(Mutex::new(BTreeMap::new()));
+
+        thread::spawn({
+            let memory = memory.clone();
+
+            move || {
+                for stream in listener.incoming() {
+                    let Ok(stream) = stream else {
+                        return;
+                    };
+                    let memory = memory.clone();
+
+                    thread::spawn(move || {
+                        let mut socket = tungstenite::accept(stream).unwrap();
+
+                        while let Ok(message) = socket.read() {
+                            let Message::Text(text) = message else {
+                                continue;
+                            };
+                            let request = serde_json::from_str::<Request>(&text).unwrap();
+
+                            for reply in handle(&memory.lock(), &request) {
+                                if socket.send(reply).is_err() {
+                                    return;
+                                }
+                            }
+                        }
+                    });
+                }
+            }
+        });
+
+        Self { address, memory }
+    }
+
+    pub fn url(&self) -> String {
+        format!("ws://{}", self.address)
+    }
+
+    /// Writes bytes at an address of the usb2snes address space.
+    pub fn write(&self, address: u32, bytes: &[u8]) {
+        let mut memory = self.memory.lock();
+
+        for (address, &byte) in (address..).zip(bytes) {
+            memory.insert(address, byte);
+        }
+    }
+}
+
+fn handle(memory: &BTreeMap<u32, u8>, request: &Request) -> Vec<Message> {
+    let results = |results: &[&str]| {
+        let response = json!({ "Results": results });
+
+        vec![Message::text(response.to_string())]
+    };
+
+    match request.opcode.as_str() {
+        "DeviceList" => results(&["Fake SNES"]),
+        "Info" => results(&["1.0.0", "Fake", "game.sfc"]),
+        "GetAddress" => {
+            let [address, length] = request.operands.as_slice() else {
+                panic!("unexpected GetAddress operands: {:?}", request.operands);
+            };
+            let address = u32::from_str_radix(address, 16).unwrap();
+            let length = usize::from_str_radix(length, 16).unwrap();
+            let data = (address..)
+                .take(length)
+                .map(|address| memory.get(&address).copied().unwrap_or_default())
+                .collect::<Vec<_>>();
+
+            data.chunks(CHUNK_SIZE)
+                .map(|chunk| Message::binary(chunk.to_vec()))
+                .collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use pretty_assertions::assert_eq;
+
+    use crate::autotracker::usb2snes::Usb2Snes;
+    use crate::autotracker::{Backend, Device};
+    use crate::pack::test_helpers::{eval, fixture_pack};
+    use crate::pack::Pack;
+
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Updates the memory watches of the pack until the condition holds.
+    fn update_until(pack: &Pack, backend: &dyn Backend, done: impl Fn() -> bool) {
+        let deadline = Instant::now() + TIMEOUT;
+
+        while !done() {
+            pack.api
+                .update_memory_watches(backend, Instant::now())
+                .unwrap();
+
+            assert!(
+                Instant::now() < deadline,
+                "timed out waiting for memory watch"
+            );
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn reads_split_memory() {
+        let server = FakeUsb2Snes::start();
+
+        server.write(0xF5_F340, &[1, 2, 3, 4, 5, 6, 7]);
+
+        let (mut usb2snes, device) = Usb2Snes::connect(&server.url()).unwrap();
+
+        assert_eq!(device, "Fake SNES");
+        assert_eq!(usb2snes.read(0x7E_F341, 5).unwrap(), [2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn memory_watch_callbacks() {
+        let server = FakeUsb2Snes::start();
+        let pack = fixture_pack("autotracking");
+        let backend = Usb2Snes::backend(server.url());
+
+        server.write(0xF5_F340, &[0x01, 0x02]);
+
+        update_until(&pack, &backend, || eval::<u32>(&pack, "return sword") == 1);
+
+        assert_eq!(eval::<u32>(&pack, "return shield"), 0x0201);
+
+        server.write(0xF5_F340, &[0x03]);
+
+        update_until(&pack, &backend, || eval::<u32>(&pack, "return sword") == 3);
+
+        assert_eq!(eval::<u32>(&pack, "return shield"), 0x0203);
+    }
+}
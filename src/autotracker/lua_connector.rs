@@ -0,0 +1,274 @@
+//! Backend for emulator scripts speaking the Lua connector protocol (BizHawk, RetroArch, …).
+//!
+//! The tracker listens on a local TCP port and the emulator script connects to it.
+//! Both sides exchange newline delimited JSON messages: the tracker sends read requests
+//! and the emulator answers each of them with the requested memory.
+
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use base64::prelude::{Engine, BASE64_STANDARD};
+use eyre::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use crate::autotracker::{Device, ThreadedBackend};
+
+pub const DEFAULT_PORT: u16 = 43884;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Memory domain requested from the emulator.
+const DOMAIN: &str = "System Bus";
+
+/// Message types of the protocol.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(into = "u8", try_from = "u8")]
+pub enum MessageType {
+    ReadBlock,
+}
+
+impl MessageType {
+    const READ_BLOCK: u8 = 0x0F;
+}
+
+impl From<MessageType> for u8 {
+    fn from(message_type: MessageType) -> Self {
+        match message_type {
+            MessageType::ReadBlock => MessageType::READ_BLOCK,
+        }
+    }
+}
+
+impl TryFrom<u8> for MessageType {
+    type Error = String;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            Self::READ_BLOCK => Ok(Self::ReadBlock),
+            _ => Err(format!("unsupported message type {value:#x}")),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    pub id: u64,
+    #[serde(rename = "type")]
+    pub message_type: MessageType,
+    pub address: u32,
+    /// Number of bytes to read.
+    pub value: usize,
+    pub domain: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub id: u64,
+    #[serde(rename = "type")]
+    pub message_type: MessageType,
+    #[serde(default)]
+    pub address: u32,
+    /// Base64 encoded memory.
+    #[serde(default)]
+    pub block: String,
+    /// Error message of the emulator script.
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+pub struct LuaConnector {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    next_id: u64,
+}
+
+impl LuaConnector {
+    /// Listens for the emulator on the port and spawns a backend for it.
+    pub fn backend(address: impl ToSocketAddrs) -> Result<ThreadedBackend> {
+        let listener = Self::bind(address)?;
+
+        Ok(Self::backend_for(listener))
+    }
+
+    /// Spawns a backend that waits for the emulator on the listener.
+    pub fn backend_for(listener: TcpListener) -> ThreadedBackend {
+        ThreadedBackend::spawn(move |cancelled| Self::accept(&listener, cancelled))
+    }
+
+    pub fn bind(address: impl ToSocketAddrs) -> Result<TcpListener> {
+        let listener = TcpListener::bind(address).context("failed to listen for emulator")?;
+
+        listener
+            .set_nonblocking(true)
+            .context("failed to make listener non-blocking")?;
+
+        Ok(listener)
+    }
+
+    /// Waits until an emulator connects or `cancelled` is set.
+    #[instrument(skip_all)]
+    pub fn accept(listener: &TcpListener, cancelled: &AtomicBool) -> Result<(Self, String)> {
+        let (stream, address) = loop {
+            if cancelled.load(Ordering::Relaxed) {
+                bail!("cancelled while waiting for emulator");
+            }
+
+            match listener.accept() {
+                Ok(connection) => break connection,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                    thread::sleep(ACCEPT_POLL_INTERVAL)
+                }
+                Err(err) => return Err(err).context("failed to accept emulator connection"),
+            }
+        };
+
+        debug!(?address, "emulator connected");
+
+        Ok((Self::new(stream)?, emulator_name(address)))
+    }
+
+    fn new(stream: TcpStream) -> Result<Self> {
+        stream
+            .set_nonblocking(false)
+            .context("failed to make stream blocking")?;
+        stream
+            .set_read_timeout(Some(TIMEOUT))
+            .context("failed to set read timeout")?;
+        stream.set_nodelay(true).ok();
+
+        let writer = stream.try_clone().context("failed to clone stream")?;
+
+        Ok(Self {
+            reader: BufReader::new(stream),
+            writer,
+            next_id: 0,
+        })
+    }
+
+    fn receive(&mut self) -> Result<Response> {
+        let mut line = Vec::new();
+
+        loop {
+            line.clear();
+
+            let read = self
+                .reader
+                .read_until(b'\n', &mut line)
+                .context("failed to receive from emulator")?;
+
+            if read == 0 {
+                bail!("emulator closed the connection");
+            }
+
+            let message = trim_message(&line);
+
+            if !message.is_empty() {
+                return serde_json::from_slice(message).context("invalid message from emulator");
+            }
+        }
+    }
+}
+
+impl Device for LuaConnector {
+    fn read(&mut self, address: u32, length: usize) -> Result<Vec<u8>> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let request = Request {
+            id,
+            message_type: MessageType::ReadBlock,
+            address,
+            value: length,
+            domain: DOMAIN.into(),
+        };
+        let mut message = serde_json::to_vec(&request).context("failed to serialize request")?;
+        message.push(b'\n');
+
+        self.writer
+            .write_all(&message)
+            .context("failed to send to emulator")?;
+
+        let response = loop {
+            let response = self.receive()?;
+
+            if response.id == id {
+                break response;
+            }
+
+            debug!(id = response.id, "ignoring stale response");
+        };
+
+        if let Some(error) = response.error {
+            bail!("emulator failed to read {address:#x}: {error}");
+        }
+
+        let data = BASE64_STANDARD
+            .decode(&response.block)
+            .context("invalid memory block from emulator")?;
+
+        if data.len() != length {
+            bail!("emulator returned {} bytes instead of {length}", data.len());
+        }
+
+        Ok(data)
+    }
+}
+
+/// Strips whitespace and the NUL padding some connector scripts add to their messages.
+fn trim_message(message: &[u8]) -> &[u8] {
+    let is_padding = |byte: &u8| byte.is_ascii_whitespace() || *byte == 0;
+    let start = message
+        .iter()
+        .position(|byte| !is_padding(byte))
+        .unwrap_or(message.len());
+    let end = message
+        .iter()
+        .rposition(|byte| !is_padding(byte))
+        .map_or(start, |end| end + 1);
+
+    &message[start..end]
+}
+
+fn emulator_name(address: SocketAddr) -> String {
+    format!("Lua connector ({address})")
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn request_json() {
+        let request = Request {
+            id: 1,
+            message_type: MessageType::ReadBlock,
+            address: 0x7ef340,
+            value: 2,
+            domain: DOMAIN.into(),
+        };
+
+        assert_eq!(
+            serde_json::to_value(request).unwrap(),
+            serde_json::json!({
+                "id": 1,
+                "type": 15,
+                "address": 0x7ef340,
+                "value": 2,
+                "domain": "System Bus",
+            })
+        );
+    }
+
+    #[test]
+    fn trims_padding() {
+        assert_eq!(trim_message(b" {}\0\n"), b"{}");
+        assert_eq!(trim_message(b"\0\n"), b"");
+    }
+}
@@ -0,0 +1,300 @@
+use std::time::{Duration, Instant};
+
+use tracing::warn;
+
+/// Interval used by watches that don't specify one.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_millis(1000);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadRequest {
+    pub address: u32,
+    pub length: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadResult {
+    pub request: ReadRequest,
+    pub data: Result<Vec<u8>, String>,
+}
+
+/// Memory read by a watch, addressed like the watched range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub address: u32,
+    pub data: Vec<u8>,
+}
+
+impl Segment {
+    /// Reads a little endian value of `size` bytes.
+    /// Returns `None` if the value isn't completely inside the segment.
+    pub fn read(&self, address: u32, size: usize) -> Option<u32> {
+        let offset = address.checked_sub(self.address)? as usize;
+        let bytes = self.data.get(offset..offset.checked_add(size)?)?;
+
+        let value = bytes
+            .iter()
+            .rev()
+            .fold(0, |value, &byte| (value << 8) | u32::from(byte));
+
+        Some(value)
+    }
+
+    pub fn read_u8(&self, address: u32) -> Option<u8> {
+        self.read(address, 1).map(|value| value as u8)
+    }
+
+    pub fn read_u16(&self, address: u32) -> Option<u16> {
+        self.read(address, 2).map(|value| value as u16)
+    }
+
+    pub fn read_u24(&self, address: u32) -> Option<u32> {
+        self.read(address, 3)
+    }
+
+    pub fn read_u32(&self, address: u32) -> Option<u32> {
+        self.read(address, 4)
+    }
+}
+
+#[derive(Debug)]
+pub struct MemoryWatch<C> {
+    pub name: String,
+    pub request: ReadRequest,
+    pub interval: Duration,
+    pub callback: C,
+    last_request: Option<Instant>,
+    pending: bool,
+    data: Option<Vec<u8>>,
+}
+
+/// Schedules the reads of memory watches and detects changes.
+#[derive(Debug)]
+pub struct MemoryWatches<C> {
+    watches: Vec<MemoryWatch<C>>,
+}
+
+impl<C> Default for MemoryWatches<C> {
+    fn default() -> Self {
+        Self {
+            watches: Vec::new(),
+        }
+    }
+}
+
+impl<C: Clone> MemoryWatches<C> {
+    /// Adds a watch, replacing any watch with the same name.
+    pub fn add(&mut self, name: String, request: ReadRequest, interval: Duration, callback: C) {
+        self.remove(&name);
+
+        self.watches.push(MemoryWatch {
+            name,
+            request,
+            interval,
+            callback,
+            last_request: None,
+            pending: false,
+            data: None,
+        });
+    }
+
+    /// Returns `true` if a watch was removed.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let len = self.watches.len();
+
+        self.watches.retain(|watch| watch.name != name);
+
+        self.watches.len() != len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.watches.is_empty()
+    }
+
+    /// Returns the reads that are due and marks them as pending.
+    /// Watches of the same range share a single read.
+    pub fn due_requests(&mut self, now: Instant) -> Vec<ReadRequest> {
+        let mut requests = Vec::new();
+
+        for watch in &mut self.watches {
+            let due = watch
+                .last_request
+                .map_or(true, |last_request| now - last_request >= watch.interval);
+
+            if watch.pending || !due {
+                continue;
+            }
+
+            watch.pending = true;
+            watch.last_request = Some(now);
+
+            if !requests.contains(&watch.request) {
+                requests.push(watch.request);
+            }
+        }
+
+        requests
+    }
+
+    /// Applies a read result and returns the watches whose memory changed.
+    pub fn apply(&mut self, result: &ReadResult) -> Vec<(String, C, Segment)> {
+        let mut changed = Vec::new();
+
+        for watch in &mut self.watches {
+            if !watch.pending || watch.request != result.request {
+                continue;
+            }
+
+            watch.pending = false;
+
+            let data = match &result.data {
+                Ok(data) => data,
+                Err(err) => {
+                    warn!("memory watch `{}` failed: {err}", watch.name);
+                    continue;
+                }
+            };
+
+            if watch.data.as_ref() == Some(data) {
+                continue;
+            }
+
+            watch.data = Some(data.clone());
+
+            let segment = Segment {
+                address: watch.request.address,
+                data: data.clone(),
+            };
+
+            changed.push((watch.name.clone(), watch.callback.clone(), segment));
+        }
+
+        changed
+    }
+
+    /// Forgets the last memory of a watch, so its callback is called again after the next read.
+    pub fn invalidate(&mut self, name: &str) {
+        for watch in &mut self.watches {
+            if watch.name == name {
+                watch.data = None;
+            }
+        }
+    }
+
+    /// Forgets all pending reads, e.g. after the backend changed.
+    pub fn reset(&mut self) {
+        for watch in &mut self.watches {
+            watch.pending = false;
+            watch.last_request = None;
+            watch.data = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    const RANGE: ReadRequest = ReadRequest {
+        address: 0x7ef340,
+        length: 4,
+    };
+
+    fn result(data: &[u8]) -> ReadResult {
+        ReadResult {
+            request: RANGE,
+            data: Ok(data.to_vec()),
+        }
+    }
+
+    fn names(changed: &[(String, (), Segment)]) -> Vec<&str> {
+        changed.iter().map(|(name, _, _)| name.as_str()).collect()
+    }
+
+    #[test]
+    fn segment_reads_little_endian() {
+        let segment = Segment {
+            address: 0x100,
+            data: vec![0x01, 0x02, 0x03, 0x04],
+        };
+
+        assert_eq!(segment.read_u8(0x101), Some(0x02));
+        assert_eq!(segment.read_u16(0x100), Some(0x0201));
+        assert_eq!(segment.read_u24(0x101), Some(0x040302));
+        assert_eq!(segment.read_u32(0x100), Some(0x04030201));
+        assert_eq!(segment.read_u16(0x103), None);
+        assert_eq!(segment.read_u8(0xff), None);
+    }
+
+    #[test]
+    fn requests_are_scheduled_by_interval() {
+        let mut watches = MemoryWatches::default();
+        let start = Instant::now();
+
+        watches.add("a".into(), RANGE, Duration::from_secs(1), ());
+
+        assert_eq!(watches.due_requests(start), [RANGE]);
+        // Still pending
+        assert_eq!(
+            watches.due_requests(start + Duration::from_secs(2)),
+            Vec::<ReadRequest>::new()
+        );
+
+        watches.apply(&result(&[0; 4]));
+
+        assert_eq!(
+            watches.due_requests(start + Duration::from_millis(500)),
+            Vec::<ReadRequest>::new()
+        );
+        assert_eq!(
+            watches.due_requests(start + Duration::from_secs(1)),
+            [RANGE]
+        );
+    }
+
+    #[test]
+    fn shared_ranges_are_read_once() {
+        let mut watches = MemoryWatches::default();
+
+        watches.add("a".into(), RANGE, DEFAULT_INTERVAL, ());
+        watches.add("b".into(), RANGE, DEFAULT_INTERVAL, ());
+
+        assert_eq!(watches.due_requests(Instant::now()), [RANGE]);
+        assert_eq!(names(&watches.apply(&result(&[1; 4]))), ["a", "b"]);
+    }
+
+    #[test]
+    fn only_changes_are_reported() {
+        let start = Instant::now();
+        let mut watches = MemoryWatches::default();
+        watches.add("a".into(), RANGE, DEFAULT_INTERVAL, ());
+
+        watches.due_requests(start);
+        assert_eq!(names(&watches.apply(&result(&[1; 4]))), ["a"]);
+
+        watches.due_requests(start + DEFAULT_INTERVAL);
+        assert_eq!(names(&watches.apply(&result(&[1; 4]))), Vec::<&str>::new());
+
+        watches.invalidate("a");
+        watches.due_requests(start + DEFAULT_INTERVAL * 2);
+        assert_eq!(names(&watches.apply(&result(&[1; 4]))), ["a"]);
+
+        watches.due_requests(start + DEFAULT_INTERVAL * 3);
+        assert_eq!(names(&watches.apply(&result(&[2; 4]))), ["a"]);
+    }
+
+    #[test]
+    fn removed_watches_are_not_read() {
+        let mut watches = MemoryWatches::default();
+
+        watches.add("a".into(), RANGE, DEFAULT_INTERVAL, ());
+
+        assert!(watches.remove("a"));
+        assert!(!watches.remove("a"));
+        assert_eq!(
+            watches.due_requests(Instant::now()),
+            Vec::<ReadRequest>::new()
+        );
+    }
+}
@@ -0,0 +1,281 @@
+//! Client for the [Universal Auto Tracker](https://github.com/black-sliver/UAT) protocol.
+//!
+//! Instead of raw memory, UAT servers expose the tracker state of a game as named variables.
+//! After connecting, the server sends its info, the client requests all variables with a sync,
+//! and from then on the server pushes every variable that changes.
+
+use std::io;
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use eyre::{bail, eyre, Context, Result};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{debug, error, info, instrument, warn};
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Message, WebSocket};
+
+use crate::autotracker::Status;
+
+/// Default address of UAT servers.
+pub const DEFAULT_URL: &str = "ws://localhost:65399";
+
+/// Latest protocol version this client understands.
+const PROTOCOL_VERSION: u32 = 0;
+
+/// How long to wait for server messages before checking whether the client was dropped.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+type Socket = WebSocket<MaybeTlsStream<TcpStream>>;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "cmd")]
+pub enum ServerCommand {
+    Info {
+        #[serde(default)]
+        name: Option<String>,
+        #[serde(default)]
+        version: Option<String>,
+        protocol: u32,
+        #[serde(default)]
+        slots: Vec<String>,
+    },
+    Var {
+        name: String,
+        value: Value,
+        #[serde(default)]
+        slot: Option<String>,
+    },
+    ErrorReply {
+        #[serde(default)]
+        name: Option<String>,
+        reason: String,
+        #[serde(default)]
+        description: Option<String>,
+    },
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "cmd")]
+pub enum ClientCommand {
+    Sync {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        slot: Option<String>,
+    },
+}
+
+/// Variable sent by the server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variable {
+    pub name: String,
+    pub value: Value,
+}
+
+/// Connection to a UAT server.
+///
+/// The connection is handled by a background thread and closed when the client is dropped.
+pub struct Uat {
+    variables: Receiver<Variable>,
+    status: Arc<Mutex<Status>>,
+    dropped: Arc<AtomicBool>,
+}
+
+impl Uat {
+    pub fn connect(url: impl Into<String>) -> Self {
+        let url = url.into();
+        let (variable_sender, variables) = mpsc::channel();
+        let status = Arc::new(Mutex::new(Status::Connecting));
+        let dropped = Arc::new(AtomicBool::new(false));
+
+        thread::spawn({
+            let status = status.clone();
+            let dropped = dropped.clone();
+
+            move || {
+                let result = run(&url, &status, &variable_sender, &dropped);
+
+                *status.lock() = match result {
+                    Ok(()) => Status::Disconnected,
+                    Err(err) => {
+                        error!("UAT connection failed: {err:?}");
+                        Status::Error(format!("{err:#}"))
+                    }
+                };
+            }
+        });
+
+        Self {
+            variables,
+            status,
+            dropped,
+        }
+    }
+
+    pub fn status(&self) -> Status {
+        self.status.lock().clone()
+    }
+
+    /// Returns all variables received since the last poll, in the order they were received.
+    pub fn poll(&self) -> Vec<Variable> {
+        self.variables.try_iter().collect()
+    }
+}
+
+impl Drop for Uat {
+    fn drop(&mut self) {
+        self.dropped.store(true, Ordering::Relaxed);
+    }
+}
+
+#[instrument(skip(status, variables, dropped))]
+fn run(
+    url: &str,
+    status: &Mutex<Status>,
+    variables: &Sender<Variable>,
+    dropped: &AtomicBool,
+) -> Result<()> {
+    let mut socket = connect(url)?;
+
+    loop {
+        if dropped.load(Ordering::Relaxed) {
+            debug!("client dropped, closing connection");
+            socket.close(None).ok();
+            socket.flush().ok();
+            return Ok(());
+        }
+
+        let text = match socket.read() {
+            Ok(Message::Text(text)) => text,
+            Ok(Message::Close(_)) => {
+                debug!("server closed the connection");
+                return Ok(());
+            }
+            Ok(_) => continue,
+            Err(tungstenite::Error::Io(err))
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                continue
+            }
+            Err(err) => return Err(err).context("failed to read from UAT server"),
+        };
+
+        let commands = match serde_json::from_str::<Vec<ServerCommand>>(&text) {
+            Ok(commands) => commands,
+            Err(err) => {
+                error!("failed to parse UAT message: {err:?}");
+                continue;
+            }
+        };
+
+        for command in commands {
+            match command {
+                ServerCommand::Info {
+                    name,
+                    version,
+                    protocol,
+                    ..
+                } => {
+                    if protocol > PROTOCOL_VERSION {
+                        bail!("unsupported UAT protocol version {protocol}");
+                    }
+
+                    let name = name.unwrap_or_else(|| "UAT".into());
+                    info!(name, version, "connected to UAT server");
+                    *status.lock() = Status::Connected { device: name };
+
+                    send(&mut socket, &[ClientCommand::Sync { slot: None }])?;
+                }
+                ServerCommand::Var { name, value, .. } => {
+                    if variables.send(Variable { name, value }).is_err() {
+                        return Ok(());
+                    }
+                }
+                ServerCommand::ErrorReply {
+                    name,
+                    reason,
+                    description,
+                } => warn!(
+                    ?name,
+                    ?description,
+                    "UAT server replied with error `{reason}`"
+                ),
+                ServerCommand::Unknown => {}
+            }
+        }
+    }
+}
+
+fn send(socket: &mut Socket, commands: &[ClientCommand]) -> Result<()> {
+    let message = serde_json::to_string(commands).context("failed to serialize commands")?;
+
+    socket
+        .send(Message::text(message))
+        .context("failed to send commands")
+}
+
+fn connect(url: &str) -> Result<Socket> {
+    let (socket, _response) =
+        tungstenite::connect(url).with_context(|| eyre!("failed to connect to UAT at {url}"))?;
+
+    if let MaybeTlsStream::Plain(stream) = socket.get_ref() {
+        stream
+            .set_read_timeout(Some(POLL_INTERVAL))
+            .context("failed to set read timeout")?;
+    }
+
+    Ok(socket)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn parses_server_commands() {
+        let commands = serde_json::from_value::<Vec<ServerCommand>>(json!([
+            {"cmd": "Info", "name": "Game", "version": "1.0", "protocol": 0},
+            {"cmd": "Var", "name": "sword", "value": 2},
+            {"cmd": "Unsupported"},
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            commands,
+            [
+                ServerCommand::Info {
+                    name: Some("Game".into()),
+                    version: Some("1.0".into()),
+                    protocol: 0,
+                    slots: Vec::new(),
+                },
+                ServerCommand::Var {
+                    name: "sword".into(),
+                    value: json!(2),
+                    slot: None,
+                },
+                ServerCommand::Unknown,
+            ]
+        );
+    }
+
+    #[test]
+    fn sync_json() {
+        assert_eq!(
+            serde_json::to_value([ClientCommand::Sync { slot: None }]).unwrap(),
+            json!([{"cmd": "Sync"}])
+        );
+    }
+}
@@ -0,0 +1,201 @@
+//! Backend for [usb2snes](https://github.com/Skarsnik/QUsb2snes) compatible servers.
+
+use std::net::TcpStream;
+use std::time::Duration;
+
+use eyre::{bail, eyre, Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Message, WebSocket};
+
+use crate::autotracker::{Device, ThreadedBackend};
+
+/// Default address of QUsb2Snes.
+pub const DEFAULT_URL: &str = "ws://localhost:23074";
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Start of WRAM in the usb2snes address space.
+const WRAM_START: u32 = 0xF5_0000;
+/// Start of SRAM in the usb2snes address space.
+const SRAM_START: u32 = 0xE0_0000;
+
+pub struct Usb2Snes {
+    socket: WebSocket<MaybeTlsStream<TcpStream>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct Request<'a> {
+    opcode: &'a str,
+    space: &'a str,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    operands: &'a [String],
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Response {
+    results: Vec<String>,
+}
+
+impl Usb2Snes {
+    /// Spawns a backend that connects to the first device of the server.
+    pub fn backend(url: impl Into<String>) -> ThreadedBackend {
+        let url = url.into();
+
+        ThreadedBackend::spawn(move |_| Self::connect(&url))
+    }
+
+    /// Connects to the server and attaches to its first device.
+    /// Returns the device name along with the connection.
+    #[instrument]
+    pub fn connect(url: &str) -> Result<(Self, String)> {
+        let (socket, _response) = tungstenite::connect(url)
+            .with_context(|| eyre!("failed to connect to usb2snes at {url}"))?;
+
+        let stream = match socket.get_ref() {
+            MaybeTlsStream::Plain(stream) => stream,
+            MaybeTlsStream::Rustls(stream) => stream.get_ref(),
+            _ => bail!("unsupported stream type"),
+        };
+
+        stream
+            .set_read_timeout(Some(TIMEOUT))
+            .context("failed to set read timeout")?;
+
+        let mut usb2snes = Self { socket };
+
+        let devices = usb2snes.query("DeviceList", &[])?;
+        let Some(device) = devices.into_iter().next() else {
+            bail!("no usb2snes devices found");
+        };
+
+        usb2snes.send("Attach", &[device.clone()])?;
+        usb2snes.send("Name", &[env!("CARGO_PKG_NAME").to_owned()])?;
+
+        // Attach has no response, query the device info to make sure it worked.
+        let info = usb2snes.query("Info", &[])?;
+        debug!(?info, "attached to {device}");
+
+        Ok((usb2snes, device))
+    }
+
+    fn send(&mut self, opcode: &str, operands: &[String]) -> Result<()> {
+        let request = Request {
+            opcode,
+            space: "SNES",
+            operands,
+        };
+        let request = serde_json::to_string(&request).context("failed to serialize request")?;
+
+        self.socket
+            .send(Message::text(request))
+            .with_context(|| eyre!("failed to send `{opcode}`"))
+    }
+
+    fn query(&mut self, opcode: &str, operands: &[String]) -> Result<Vec<String>> {
+        self.send(opcode, operands)?;
+
+        loop {
+            match self
+                .socket
+                .read()
+                .with_context(|| eyre!("no response to `{opcode}`"))?
+            {
+                Message::Text(text) => {
+                    let response = serde_json::from_str::<Response>(&text)
+                        .with_context(|| eyre!("invalid response to `{opcode}`"))?;
+
+                    return Ok(response.results);
+                }
+                Message::Close(_) => bail!("usb2snes closed the connection"),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Device for Usb2Snes {
+    fn read(&mut self, address: u32, length: usize) -> Result<Vec<u8>> {
+        let Some(usb2snes_address) = snes_to_usb2snes(address) else {
+            bail!("address {address:X} is not mapped to memory");
+        };
+        let operands = [format!("{usb2snes_address:X}"), format!("{length:X}")];
+
+        self.send("GetAddress", &operands)?;
+
+        let mut data = Vec::with_capacity(length);
+
+        // The data may be split across multiple messages.
+        while data.len() < length {
+            match self.socket.read().context("failed to read memory")? {
+                Message::Binary(bytes) => data.extend_from_slice(&bytes),
+                Message::Close(_) => bail!("usb2snes closed the connection"),
+                _ => {}
+            }
+        }
+
+        data.truncate(length);
+
+        Ok(data)
+    }
+}
+
+/// Maps a LoROM SNES bus address, as used by packs, to the usb2snes address space.
+/// Returns `None` for addresses that aren't backed by memory, like the I/O registers.
+pub fn snes_to_usb2snes(address: u32) -> Option<u32> {
+    let bank = (address >> 16) & 0xFF;
+    let offset = address & 0xFFFF;
+
+    if (0x7E..=0x7F).contains(&bank) {
+        return Some(((bank - 0x7E) << 16) + offset + WRAM_START);
+    }
+
+    // Banks 0x80-0xFF mirror banks 0x00-0x7F.
+    let bank = bank & 0x7F;
+
+    match (bank, offset) {
+        // Mirror of the first 8 KiB of WRAM
+        (0x00..=0x3F, 0x0000..=0x1FFF) => Some(offset + WRAM_START),
+        // SRAM, 32 KiB per bank
+        (0x70..=0x7D, 0x0000..=0x7FFF) => Some((bank - 0x70) * 0x8000 + offset + SRAM_START),
+        // ROM, 32 KiB per bank
+        (_, 0x8000..=0xFFFF) => Some(bank * 0x8000 + offset - 0x8000),
+        // Mirror of the ROM in the upper half of the bank
+        (0x40..=0x6F, _) => Some(bank * 0x8000 + offset),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn maps_snes_addresses() {
+        // WRAM and its mirrors
+        assert_eq!(snes_to_usb2snes(0x7E_F340), Some(0xF5_F340));
+        assert_eq!(snes_to_usb2snes(0x7F_0000), Some(0xF6_0000));
+        assert_eq!(snes_to_usb2snes(0x00_0010), Some(0xF5_0010));
+        assert_eq!(snes_to_usb2snes(0x80_1FFF), Some(0xF5_1FFF));
+
+        // SRAM and its mirrors
+        assert_eq!(snes_to_usb2snes(0x70_0010), Some(0xE0_0010));
+        assert_eq!(snes_to_usb2snes(0x71_0000), Some(0xE0_8000));
+        assert_eq!(snes_to_usb2snes(0xF0_7FFF), Some(0xE0_7FFF));
+
+        // ROM and its mirrors
+        assert_eq!(snes_to_usb2snes(0x00_8000), Some(0x00_0000));
+        assert_eq!(snes_to_usb2snes(0x01_8000), Some(0x00_8000));
+        assert_eq!(snes_to_usb2snes(0x80_FFFF), Some(0x00_7FFF));
+        assert_eq!(snes_to_usb2snes(0x40_0000), Some(0x20_0000));
+
+        // I/O registers
+        assert_eq!(snes_to_usb2snes(0x00_2100), None);
+        assert_eq!(snes_to_usb2snes(0x80_4218), None);
+    }
+}
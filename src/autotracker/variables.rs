@@ -0,0 +1,223 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+/// Latest values of the variables received from a UAT server.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VariableStore {
+    values: BTreeMap<String, Value>,
+}
+
+impl VariableStore {
+    pub fn read(&self, name: &str) -> Option<&Value> {
+        self.values.get(name)
+    }
+}
+
+#[derive(Debug)]
+pub struct VariableWatch<C> {
+    pub name: String,
+    pub variables: Vec<String>,
+    pub interval: Duration,
+    pub callback: C,
+    /// Watched variables that changed since the last call, in the order they changed.
+    changed: Vec<String>,
+    last_call: Option<Instant>,
+}
+
+/// Keeps the latest variable values and decides which watches to call.
+#[derive(Debug)]
+pub struct VariableWatches<C> {
+    store: VariableStore,
+    watches: Vec<VariableWatch<C>>,
+}
+
+impl<C> Default for VariableWatches<C> {
+    fn default() -> Self {
+        Self {
+            store: VariableStore::default(),
+            watches: Vec::new(),
+        }
+    }
+}
+
+impl<C: Clone> VariableWatches<C> {
+    /// Adds a watch, replacing any watch with the same name.
+    /// Variables that are already known count as changed, so the watch sees the current state.
+    pub fn add(&mut self, name: String, variables: Vec<String>, interval: Duration, callback: C) {
+        self.remove(&name);
+
+        let changed = variables
+            .iter()
+            .filter(|variable| self.store.values.contains_key(*variable))
+            .cloned()
+            .collect();
+
+        self.watches.push(VariableWatch {
+            name,
+            variables,
+            interval,
+            callback,
+            changed,
+            last_call: None,
+        });
+    }
+
+    /// Returns `true` if a watch was removed.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let len = self.watches.len();
+
+        self.watches.retain(|watch| watch.name != name);
+
+        self.watches.len() != len
+    }
+
+    pub fn store(&self) -> &VariableStore {
+        &self.store
+    }
+
+    /// Stores a variable and marks it as changed for the watches of it.
+    pub fn set(&mut self, name: String, value: Value) {
+        if self.store.values.get(&name) == Some(&value) {
+            return;
+        }
+
+        for watch in &mut self.watches {
+            if watch.variables.contains(&name) && !watch.changed.contains(&name) {
+                watch.changed.push(name.clone());
+            }
+        }
+
+        self.store.values.insert(name, value);
+    }
+
+    /// Returns the watches that are due along with their changed variables.
+    /// A watch is called at most once per interval, changes in between are batched.
+    pub fn due(&mut self, now: Instant) -> Vec<(String, C, Vec<String>)> {
+        let mut due = Vec::new();
+
+        for watch in &mut self.watches {
+            let interval_passed = watch
+                .last_call
+                .map_or(true, |last_call| now - last_call >= watch.interval);
+
+            if watch.changed.is_empty() || !interval_passed {
+                continue;
+            }
+
+            watch.last_call = Some(now);
+
+            let changed = std::mem::take(&mut watch.changed);
+            due.push((watch.name.clone(), watch.callback.clone(), changed));
+        }
+
+        due
+    }
+
+    /// Forgets all variables, e.g. after the server changed.
+    pub fn reset(&mut self) {
+        self.store.values.clear();
+
+        for watch in &mut self.watches {
+            watch.changed.clear();
+            watch.last_call = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+
+    const INTERVAL: Duration = Duration::from_secs(1);
+
+    fn due(watches: &mut VariableWatches<()>, now: Instant) -> Vec<(String, Vec<String>)> {
+        watches
+            .due(now)
+            .into_iter()
+            .map(|(name, (), changed)| (name, changed))
+            .collect()
+    }
+
+    fn watch(name: &str, changed: &[&str]) -> (String, Vec<String>) {
+        let changed = changed.iter().map(|&variable| variable.into()).collect();
+
+        (name.into(), changed)
+    }
+
+    #[test]
+    fn changes_are_reported_to_watches_of_the_variable() {
+        let mut watches = VariableWatches::default();
+        let now = Instant::now();
+
+        watches.add("a".into(), vec!["sword".into()], INTERVAL, ());
+        watches.add("b".into(), vec!["shield".into()], INTERVAL, ());
+
+        watches.set("sword".into(), json!(1));
+
+        assert_eq!(due(&mut watches, now), [watch("a", &["sword"])]);
+        assert_eq!(watches.store().read("sword"), Some(&json!(1)));
+
+        // Unchanged values are not reported
+        watches.set("sword".into(), json!(1));
+
+        assert!(due(&mut watches, now + INTERVAL).is_empty());
+    }
+
+    #[test]
+    fn changes_within_the_interval_are_batched() {
+        let mut watches = VariableWatches::default();
+        let now = Instant::now();
+
+        watches.add(
+            "a".into(),
+            vec!["sword".into(), "shield".into()],
+            INTERVAL,
+            (),
+        );
+
+        watches.set("sword".into(), json!(1));
+        assert_eq!(due(&mut watches, now), [watch("a", &["sword"])]);
+
+        watches.set("shield".into(), json!(1));
+        watches.set("sword".into(), json!(2));
+        assert!(due(&mut watches, now + INTERVAL / 2).is_empty());
+        assert_eq!(
+            due(&mut watches, now + INTERVAL),
+            [watch("a", &["shield", "sword"])]
+        );
+    }
+
+    #[test]
+    fn new_watches_see_known_variables() {
+        let mut watches = VariableWatches::default();
+
+        watches.set("sword".into(), json!(1));
+        watches.add(
+            "a".into(),
+            vec!["sword".into(), "shield".into()],
+            INTERVAL,
+            (),
+        );
+
+        assert_eq!(due(&mut watches, Instant::now()), [watch("a", &["sword"])]);
+    }
+
+    #[test]
+    fn removed_watches_are_not_called() {
+        let mut watches = VariableWatches::default();
+
+        watches.add("a".into(), vec!["sword".into()], INTERVAL, ());
+
+        assert!(watches.remove("a"));
+        assert!(!watches.remove("a"));
+
+        watches.set("sword".into(), json!(1));
+
+        assert!(due(&mut watches, Instant::now()).is_empty());
+    }
+}
@@ -1,3 +1,5 @@
+pub mod archipelago;
+pub mod autotracker;
 pub mod cli;
 pub mod pack;
 pub mod ui;
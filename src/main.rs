@@ -103,7 +103,7 @@ impl App {
                 let pack_dir = env::current_dir().unwrap().join("packs");
                 Self::PackPicker(ui::PackPicker::new(pack_dir))
             }
-            Some(pack) => Self::Tracker(ui::Tracker::new(pack)),
+            Some(pack) => Self::Tracker(ui::Tracker::new(pack).offer_restore()),
         }
     }
 }
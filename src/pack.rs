@@ -1,16 +1,19 @@
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use eyre::{eyre, Context, Result};
+use eyre::{bail, eyre, Context, Result};
 pub use manifest::Manifest;
 use serde::{Deserialize, Serialize};
-use tracing::debug;
+use tracing::{debug, warn};
 
-use crate::pack::api::Api;
+use crate::pack::api::{Api, Tracker};
+pub use crate::pack::state::State;
 
 pub mod api;
+pub mod autosave;
 pub mod manifest;
 pub mod rule;
+pub mod state;
 
 pub struct Pack {
     pub root: PathBuf,
@@ -43,12 +46,71 @@ impl Pack {
             .exec()
             .with_context(|| eyre!("error executing {init_path:?}"))?;
 
+        let unresolved_references = api.with_tracker(Tracker::unresolved_references)?;
+
+        for (location_id, reference) in unresolved_references {
+            warn!("unresolved reference `{reference}` in location `{location_id}`");
+        }
+
+        let unknown_hosted_items = api.with_tracker(Tracker::unknown_hosted_items)?;
+
+        for (section_id, code) in unknown_hosted_items {
+            warn!("unknown hosted item `{code}` in section `{section_id}`");
+        }
+
         Ok(Self {
             root,
             manifest,
             api,
         })
     }
+
+    pub fn state(&self) -> Result<State> {
+        let pack = state::PackInfo {
+            uid: self.manifest.package_uid.clone(),
+            variant: self
+                .api
+                .with_tracker(|tracker| tracker.variant_uid().clone())?,
+            version: self.manifest.package_version.clone(),
+        };
+
+        Ok(State::new(pack, self.api.tracker_state()?))
+    }
+
+    pub fn load_state(&self, state: &State) -> Result<()> {
+        if state.pack.uid != self.manifest.package_uid {
+            bail!(
+                "state belongs to pack `{}` instead of `{}`",
+                state.pack.uid,
+                self.manifest.package_uid
+            );
+        }
+
+        let variant_uid = self
+            .api
+            .with_tracker(|tracker| tracker.variant_uid().clone())?;
+
+        if state.pack.variant != variant_uid {
+            warn!(
+                "state belongs to variant `{}` instead of `{}`, \
+                 items and sections missing from the variant are skipped",
+                state.pack.variant.as_str(),
+                variant_uid.as_str()
+            );
+        }
+
+        self.api.load_tracker_state(&state.state)
+    }
+
+    pub fn save_state_to(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.state()?.save(path)
+    }
+
+    pub fn load_state_from(&self, path: impl AsRef<Path>) -> Result<()> {
+        let state = State::load(path)?;
+
+        self.load_state(&state)
+    }
 }
 
 impl Drop for Pack {
@@ -66,3 +128,31 @@ impl VariantUID {
         &self.0
     }
 }
+
+impl From<&str> for VariantUID {
+    fn from(uid: &str) -> Self {
+        Self(uid.into())
+    }
+}
+
+#[cfg(test)]
+pub mod test_helpers {
+    use std::path::Path;
+
+    use mlua::FromLua;
+
+    use super::{Pack, VariantUID};
+
+    /// Loads the standard variant of a pack in `tests/fixtures`.
+    pub fn fixture_pack(name: &str) -> Pack {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name);
+
+        Pack::load(root, &VariantUID::from("standard")).unwrap()
+    }
+
+    pub fn eval<T: FromLua>(pack: &Pack, code: &str) -> T {
+        pack.api.lua().load(code).eval().unwrap()
+    }
+}
@@ -1,20 +1,34 @@
 use std::fmt::Debug;
 use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
 use eyre::{Context, Result};
-use mlua::{AnyUserData, FromLua, IntoLua, Lua, LuaOptions, MultiValue, StdLib, Table, Value};
+use mlua::{
+    AnyUserData, FromLua, IntoLua, Lua, LuaOptions, MultiValue, StdLib, Table, Value, VmState,
+};
 
 use archipelago::Archipelago;
-use script_host::ScriptHost;
+pub(crate) use script_host::ScriptHost;
 use strum::{EnumIs, FromRepr};
-use tracing::{info, instrument, warn};
+use tracing::{error, info, instrument, warn};
 pub use tracker::Tracker;
+use tracker::{section_id, Change, Click, SectionObject, TrackerState};
 
+use crate::archipelago::protocol::ClientPacket;
+use crate::archipelago::Event;
+use crate::autotracker::uat::Uat;
+use crate::autotracker::Backend;
+use crate::pack::rule::Evaluator;
 use crate::pack::VariantUID;
 
 mod archipelago;
 mod script_host;
+mod segment;
 pub mod tracker;
+mod variable_store;
+
+/// Limit for handlers causing further changes, to break infinite loops.
+const MAX_CHANGE_DISPATCH_ROUNDS: usize = 16;
 
 pub struct Api {
     lua: Lua,
@@ -101,6 +115,364 @@ impl Api {
     pub fn lua(&self) -> &Lua {
         &self.lua
     }
+
+    /// Clicks an item and returns `true` if its state changed.
+    ///
+    /// The callbacks of Lua items may access the tracker, so they are called without borrowing
+    /// it. Their changes are recorded by [`Tracker::take_changes`].
+    pub fn click_item(&self, index: usize, click: Click) -> Result<bool> {
+        let lua_item =
+            self.with_tracker(|tracker| tracker.items().get(index)?.lua_item().cloned())?;
+
+        match lua_item {
+            Some(lua_item) => Ok(lua_item.click(click)),
+            None => self.with_tracker_mut(|tracker| tracker.click_item(index, click)),
+        }
+    }
+
+    /// Clears all visible sections of a location and collects their hosted items, or restores
+    /// them if all are cleared already.
+    ///
+    /// Hosted Lua items are collected with a left click and uncollected with a right click,
+    /// without borrowing the tracker like in [`click_item`](Self::click_item).
+    pub fn toggle_location_cleared(&self, id: &str) -> Result<()> {
+        let found = self.with_tracker(|tracker| {
+            let location = tracker.location(id)?;
+            let evaluator = Evaluator::new(tracker, &self.lua);
+            let sections = location
+                .sections
+                .iter()
+                .filter(|section| evaluator.section_visible(section))
+                .collect::<Vec<_>>();
+            let cleared = !sections
+                .iter()
+                .all(|section| tracker.is_section_cleared(section));
+            let section_ids = sections
+                .iter()
+                .map(|section| section_id(&location.id, section))
+                .collect::<Vec<_>>();
+
+            Some((section_ids, cleared))
+        })?;
+
+        let Some((section_ids, cleared)) = found else {
+            error!("unknown location `{id}`");
+            return Ok(());
+        };
+
+        let lua_items =
+            self.with_tracker_mut(|tracker| tracker.set_sections_cleared(&section_ids, cleared))?;
+
+        for (lua_item, code) in lua_items {
+            if (lua_item.provides_code(&code) > 0) != cleared {
+                lua_item.click(if cleared { Click::Left } else { Click::Right });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// State of the tracker, including the states returned by the `SaveFunc`s of Lua items.
+    pub fn tracker_state(&self) -> Result<TrackerState> {
+        let (mut state, lua_items) =
+            self.with_tracker(|tracker| (tracker.state(), tracker.lua_items()))?;
+
+        for (id, lua_item) in lua_items {
+            state.items.insert(id, lua_item.save().unwrap_or_default());
+        }
+
+        Ok(state)
+    }
+
+    /// Restores a state returned by [`tracker_state`](Self::tracker_state).
+    pub fn load_tracker_state(&self, state: &TrackerState) -> Result<()> {
+        let lua_items = self.with_tracker_mut(|tracker| {
+            tracker.load_state(state);
+            tracker.lua_items()
+        })?;
+
+        for (id, lua_item) in lua_items {
+            if let Some(data) = state.items.get(&id).filter(|data| !data.is_null()) {
+                lua_item.load(data);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Calls the `Archipelago` handlers registered by the pack.
+    #[instrument(skip(self))]
+    pub fn handle_archipelago_event(&self, event: &Event) -> Result<()> {
+        Archipelago::handle_event(&self.lua, event).context("failed to handle archipelago event")
+    }
+
+    /// Takes the packets the pack requested to be sent to the Archipelago server.
+    pub fn take_archipelago_packets(&self) -> Result<Vec<ClientPacket>> {
+        let archipelago = self
+            .lua
+            .globals()
+            .get::<AnyUserData>("Archipelago")
+            .context("failed to get `Archipelago` global")?;
+        let mut archipelago = archipelago
+            .borrow_mut::<Archipelago>()
+            .context("failed to borrow archipelago mutably")?;
+
+        Ok(archipelago.take_pending_packets())
+    }
+
+    /// Calls the code watches and section changed handlers for the changes of the tracker.
+    /// Changes made by the handlers are dispatched as well.
+    #[instrument(skip_all)]
+    pub fn dispatch_tracker_changes(&self) -> Result<()> {
+        let script_host = self
+            .lua
+            .globals()
+            .get::<AnyUserData>("ScriptHost")
+            .context("failed to get `ScriptHost` global")?;
+
+        for _ in 0..MAX_CHANGE_DISPATCH_ROUNDS {
+            let calls = self.with_tracker_mut(|tracker| -> Result<_> {
+                let changes = tracker.take_changes();
+                let script_host = script_host
+                    .borrow::<ScriptHost>()
+                    .context("failed to borrow script host immutably")?;
+                let mut calls = Vec::new();
+
+                for change in changes {
+                    match change {
+                        Change::Item(index) => {
+                            let Some(item) = tracker.items().get(index) else {
+                                continue;
+                            };
+
+                            for watch in &script_host.code_watches {
+                                if watch.code == "*" || item.has_code(&watch.code) {
+                                    let code = watch.code.as_str().into_lua(&self.lua)?;
+
+                                    calls.push((watch.callback.clone(), code));
+                                }
+                            }
+                        }
+                        Change::Section(id) => {
+                            for (_, handler) in &script_host.section_changed_handlers {
+                                // Like PopTracker, handlers get the section object
+                                let section =
+                                    SectionObject { id: id.clone() }.into_lua(&self.lua)?;
+
+                                calls.push((handler.clone(), section));
+                            }
+                        }
+                    }
+                }
+
+                Ok(calls)
+            })??;
+
+            if calls.is_empty() {
+                return Ok(());
+            }
+
+            // The handlers may change the tracker, so neither it nor the script host is borrowed.
+            for (callback, argument) in calls {
+                if let Err(err) = callback.call::<()>(argument) {
+                    error!("change handler failed: {err}");
+                }
+            }
+        }
+
+        error!(
+            "tracker still changing after {MAX_CHANGE_DISPATCH_ROUNDS} rounds of handlers, \
+             handlers probably change each other's state in a loop"
+        );
+
+        Ok(())
+    }
+
+    /// Calls the frame handlers with the seconds elapsed since the last frame.
+    ///
+    /// Handlers are interrupted once they took `budget` in total,
+    /// the remaining handlers are skipped until the next frame.
+    /// The next frame starts with the first skipped handler,
+    /// so a slow handler can't starve the ones after it.
+    /// Returns `true` if the pack has frame handlers.
+    #[instrument(skip_all)]
+    pub fn run_frame_handlers(&self, elapsed: Duration, budget: Duration) -> Result<bool> {
+        let script_host = self
+            .lua
+            .globals()
+            .get::<AnyUserData>("ScriptHost")
+            .context("failed to get `ScriptHost` global")?;
+        let (handlers, start) = {
+            let script_host = script_host
+                .borrow::<ScriptHost>()
+                .context("failed to borrow script host immutably")?;
+
+            (
+                script_host.frame_handlers.clone(),
+                script_host.frame_handler_start,
+            )
+        };
+
+        if handlers.is_empty() {
+            return Ok(false);
+        }
+
+        let deadline = Instant::now() + budget;
+
+        self.lua.set_interrupt(move |_lua| {
+            if Instant::now() >= deadline {
+                return Err(mlua::Error::runtime("frame handler time budget exceeded"));
+            }
+
+            Ok(VmState::Continue)
+        });
+
+        let start = start % handlers.len();
+        let mut first_skipped = None;
+
+        for index in (start..handlers.len()).chain(0..start) {
+            let (name, handler) = &handlers[index];
+
+            if Instant::now() >= deadline {
+                warn!("frame handler time budget exceeded, skipping `{name}`");
+                first_skipped.get_or_insert(index);
+                continue;
+            }
+
+            if let Err(err) = handler.call::<()>(elapsed.as_secs_f64()) {
+                error!("frame handler `{name}` failed: {err}");
+            }
+        }
+
+        self.lua.remove_interrupt();
+
+        if let Some(index) = first_skipped {
+            script_host
+                .borrow_mut::<ScriptHost>()
+                .context("failed to borrow script host mutably")?
+                .frame_handler_start = index;
+        }
+
+        Ok(true)
+    }
+
+    /// Applies finished memory reads, calls the callbacks of changed watches and requests
+    /// the reads that are due.
+    #[instrument(skip_all)]
+    pub fn update_memory_watches(&self, backend: &dyn Backend, now: Instant) -> Result<()> {
+        let script_host = self
+            .lua
+            .globals()
+            .get::<AnyUserData>("ScriptHost")
+            .context("failed to get `ScriptHost` global")?;
+
+        let changed = {
+            let mut script_host = script_host
+                .borrow_mut::<ScriptHost>()
+                .context("failed to borrow script host mutably")?;
+            let mut changed = Vec::new();
+
+            for result in backend.poll() {
+                changed.extend(script_host.memory_watches.apply(&result));
+            }
+
+            if backend.status().is_connected() {
+                for request in script_host.memory_watches.due_requests(now) {
+                    backend.request(request);
+                }
+            }
+
+            changed
+        };
+
+        // The callbacks may add or remove watches, so the script host must not be borrowed.
+        for (name, callback, segment) in changed {
+            match callback.call::<Value>(segment) {
+                // Like PopTracker, a callback returning false wants to be called again.
+                Ok(Value::Boolean(false)) => {
+                    script_host
+                        .borrow_mut::<ScriptHost>()
+                        .context("failed to borrow script host mutably")?
+                        .memory_watches
+                        .invalidate(&name);
+                }
+                Ok(_) => {}
+                Err(err) => error!("memory watch `{name}` failed: {err}"),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Stores the variables received from the UAT server and calls the callbacks of the
+    /// variable watches that are due.
+    #[instrument(skip_all)]
+    pub fn update_variable_watches(&self, uat: &Uat, now: Instant) -> Result<()> {
+        let script_host = self
+            .lua
+            .globals()
+            .get::<AnyUserData>("ScriptHost")
+            .context("failed to get `ScriptHost` global")?;
+
+        let (store, due) = {
+            let mut script_host = script_host
+                .borrow_mut::<ScriptHost>()
+                .context("failed to borrow script host mutably")?;
+
+            for variable in uat.poll() {
+                script_host
+                    .variable_watches
+                    .set(variable.name, variable.value);
+            }
+
+            let due = script_host.variable_watches.due(now);
+
+            (script_host.variable_watches.store().clone(), due)
+        };
+
+        // The callbacks may add or remove watches, so the script host must not be borrowed.
+        for (name, callback, changed) in due {
+            if let Err(err) = callback.call::<()>((store.clone(), changed)) {
+                error!("variable watch `{name}` failed: {err}");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Forgets the state of all memory watches, e.g. after the backend changed.
+    pub fn reset_memory_watches(&self) -> Result<()> {
+        let script_host = self
+            .lua
+            .globals()
+            .get::<AnyUserData>("ScriptHost")
+            .context("failed to get `ScriptHost` global")?;
+
+        script_host
+            .borrow_mut::<ScriptHost>()
+            .context("failed to borrow script host mutably")?
+            .memory_watches
+            .reset();
+
+        Ok(())
+    }
+
+    /// Forgets all variables, e.g. after the UAT server changed.
+    pub fn reset_variable_watches(&self) -> Result<()> {
+        let script_host = self
+            .lua
+            .globals()
+            .get::<AnyUserData>("ScriptHost")
+            .context("failed to get `ScriptHost` global")?;
+
+        script_host
+            .borrow_mut::<ScriptHost>()
+            .context("failed to borrow script host mutably")?
+            .variable_watches
+            .reset();
+
+        Ok(())
+    }
 }
 
 fn stdlib() -> StdLib {
@@ -116,7 +488,7 @@ fn stdlib() -> StdLib {
     .fold(StdLib::NONE, |libs, lib| libs | lib)
 }
 
-#[derive(FromRepr, EnumIs, Copy, Clone)]
+#[derive(FromRepr, EnumIs, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
 #[repr(i32)]
 pub enum AccessabilityLevel {
     None = 0,
@@ -160,3 +532,39 @@ impl FromLua for AccessabilityLevel {
             .ok_or_else(|| mlua::Error::runtime(format!("invalid accessibility level: {value:?}")))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::pack::test_helpers::fixture_pack;
+    use crate::pack::Pack;
+
+    #[test]
+    fn toggling_locations_skips_hidden_sections() {
+        let pack = fixture_pack("lua_items");
+        let cleared = |pack: &Pack| {
+            pack.api
+                .with_tracker(|tracker| {
+                    let tower = tracker.location("Tower").unwrap();
+
+                    tower
+                        .sections
+                        .iter()
+                        .map(|section| tracker.is_section_cleared(section))
+                        .collect::<Vec<_>>()
+                })
+                .unwrap()
+        };
+
+        assert_eq!(cleared(&pack), [false, false]);
+
+        pack.api.toggle_location_cleared("Tower").unwrap();
+
+        assert_eq!(cleared(&pack), [true, false]);
+
+        pack.api.toggle_location_cleared("Tower").unwrap();
+
+        assert_eq!(cleared(&pack), [false, false]);
+    }
+}
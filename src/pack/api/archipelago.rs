@@ -1,10 +1,23 @@
 use std::path::PathBuf;
 
-use mlua::{Function, UserData, UserDataFields, UserDataMethods, Value};
-use tracing::{debug, debug_span};
+use mlua::{
+    AnyUserData, Function, IntoLuaMulti, Lua, LuaSerdeExt, UserData, UserDataFields,
+    UserDataMethods, Value,
+};
+use tracing::{debug, debug_span, error};
+
+use crate::archipelago::protocol::{ClientPacket, Get, Id, SetNotify};
+use crate::archipelago::Event;
 
 pub struct Archipelago {
     root: PathBuf,
+    /// Slot number, `-1` while not connected.
+    player_number: i64,
+    team_number: i64,
+    checked_locations: Vec<Id>,
+    missing_locations: Vec<Id>,
+    /// Packets requested by the pack that still need to be sent to the server.
+    pending_packets: Vec<ClientPacket>,
     clear_handlers: Vec<(String, Function)>,
     item_handlers: Vec<(String, Function)>,
     location_handlers: Vec<(String, Function)>,
@@ -16,6 +29,11 @@ impl Archipelago {
     pub fn new(root: impl Into<PathBuf>) -> Self {
         Self {
             root: root.into(),
+            player_number: -1,
+            team_number: 0,
+            checked_locations: Vec::new(),
+            missing_locations: Vec::new(),
+            pending_packets: Vec::new(),
             clear_handlers: Vec::new(),
             item_handlers: Vec::new(),
             location_handlers: Vec::new(),
@@ -23,6 +41,115 @@ impl Archipelago {
             set_reply_handlers: Vec::new(),
         }
     }
+
+    /// Takes the packets the pack wants to send to the server.
+    pub fn take_pending_packets(&mut self) -> Vec<ClientPacket> {
+        std::mem::take(&mut self.pending_packets)
+    }
+
+    fn is_connected(&self) -> bool {
+        self.player_number >= 0
+    }
+
+    /// Calls the handlers registered for the event.
+    pub fn handle_event(lua: &Lua, event: &Event) -> mlua::Result<()> {
+        let archipelago = lua.globals().get::<AnyUserData>("Archipelago")?;
+
+        match event {
+            Event::Connected {
+                team,
+                slot,
+                slot_data,
+                checked_locations,
+                missing_locations,
+            } => {
+                let slot_data = lua.to_value(slot_data)?;
+                let handlers = {
+                    let mut this = archipelago.borrow_mut::<Self>()?;
+
+                    this.player_number = *slot;
+                    this.team_number = *team;
+                    // Both are set before the clear handlers run, so they see a consistent state.
+                    // The checked locations are reported to the location handlers afterwards.
+                    this.checked_locations.clone_from(checked_locations);
+                    this.missing_locations.clone_from(missing_locations);
+                    this.pending_packets.clear();
+
+                    this.clear_handlers.clone()
+                };
+
+                call_handlers(&handlers, slot_data)
+            }
+            Event::ItemReceived {
+                index,
+                item,
+                item_name,
+                player,
+            } => {
+                let handlers = archipelago.borrow::<Self>()?.item_handlers.clone();
+
+                call_handlers(&handlers, (*index, *item, item_name.as_str(), *player))
+            }
+            Event::LocationChecked {
+                location,
+                location_name,
+            } => {
+                let handlers = {
+                    let mut this = archipelago.borrow_mut::<Self>()?;
+
+                    this.missing_locations.retain(|missing| missing != location);
+
+                    if !this.checked_locations.contains(location) {
+                        this.checked_locations.push(*location);
+                    }
+
+                    this.location_handlers.clone()
+                };
+
+                call_handlers(&handlers, (*location, location_name.as_str()))
+            }
+            Event::Retrieved { keys } => {
+                let handlers = archipelago.borrow::<Self>()?.retrieved_handlers.clone();
+
+                for (key, value) in keys {
+                    call_handlers(&handlers, (key.as_str(), lua.to_value(value)?));
+                }
+            }
+            Event::SetReply {
+                key,
+                value,
+                original_value,
+            } => {
+                let handlers = archipelago.borrow::<Self>()?.set_reply_handlers.clone();
+                let args = (
+                    key.as_str(),
+                    lua.to_value(value)?,
+                    lua.to_value(original_value)?,
+                );
+
+                call_handlers(&handlers, args)
+            }
+            Event::Disconnected { .. } => {
+                let mut this = archipelago.borrow_mut::<Self>()?;
+
+                this.player_number = -1;
+                this.pending_packets.clear();
+            }
+            Event::Refused { .. } => {}
+        }
+
+        Ok(())
+    }
+}
+
+/// Calls every handler with the same arguments.
+/// A failing handler doesn't keep the remaining handlers from being called.
+fn call_handlers(handlers: &[(String, Function)], args: impl IntoLuaMulti + Clone) {
+    for (name, handler) in handlers {
+        if let Err(err) = handler.call::<()>(args.clone()) {
+            error!("archipelago handler `{name}` failed: {err}");
+        }
+    }
 }
 
 impl Drop for Archipelago {
@@ -32,9 +159,43 @@ impl Drop for Archipelago {
 }
 
 impl UserData for Archipelago {
-    fn add_fields<F: UserDataFields<Self>>(_fields: &mut F) {}
+    fn add_fields<F: UserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("PlayerNumber", |_, this| Ok(this.player_number));
+        fields.add_field_method_get("TeamNumber", |_, this| Ok(this.team_number));
+        fields.add_field_method_get("CheckedLocations", |_, this| {
+            Ok(this.checked_locations.clone())
+        });
+        fields.add_field_method_get("MissingLocations", |_, this| {
+            Ok(this.missing_locations.clone())
+        });
+    }
 
     fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method_mut("Get", |_lua, this, keys: Vec<String>| {
+            let _span = debug_span!("Archipelago::Get", ?keys).entered();
+
+            if !this.is_connected() {
+                return Ok(false);
+            }
+
+            this.pending_packets.push(ClientPacket::Get(Get { keys }));
+
+            Ok(true)
+        });
+
+        methods.add_method_mut("SetNotify", |_lua, this, keys: Vec<String>| {
+            let _span = debug_span!("Archipelago::SetNotify", ?keys).entered();
+
+            if !this.is_connected() {
+                return Ok(false);
+            }
+
+            this.pending_packets
+                .push(ClientPacket::SetNotify(SetNotify { keys }));
+
+            Ok(true)
+        });
+
         methods.add_method_mut(
             "AddClearHandler",
             |_lua, this, (name, callback): (String, Value)| {
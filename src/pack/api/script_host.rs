@@ -1,15 +1,65 @@
 use std::path::PathBuf;
+use std::time::Duration;
 
-use mlua::{ErrorContext, UserData, UserDataFields, UserDataMethods, UserDataRef};
+use fnv::FnvHashMap;
+use mlua::{
+    AnyUserData, ErrorContext, Function, Lua, UserData, UserDataFields, UserDataMethods,
+    UserDataRef,
+};
 use tracing::{debug, debug_span, info_span, trace};
 
+use crate::autotracker::memory::DEFAULT_INTERVAL;
+use crate::autotracker::{MemoryWatches, ReadRequest, VariableWatches};
+use crate::pack::api::tracker::{LuaItem, Tracker};
+use crate::pack::rule::ArgType;
+
 pub struct ScriptHost {
     root: PathBuf,
+    pub(super) memory_watches: MemoryWatches<Function>,
+    pub(super) variable_watches: VariableWatches<Function>,
+    pub(super) code_watches: Vec<CodeWatch>,
+    pub(super) section_changed_handlers: Vec<(String, Function)>,
+    pub(super) frame_handlers: Vec<(String, Function)>,
+    /// Index of the frame handler called first in the next frame.
+    pub(super) frame_handler_start: usize,
+    /// Types of the arguments of functions called from rules, by function name.
+    call_arg_types: FnvHashMap<String, Vec<ArgType>>,
+}
+
+pub(super) struct CodeWatch {
+    pub name: String,
+    /// Item code to watch, `*` watches all items.
+    pub code: String,
+    pub callback: Function,
 }
 
 impl ScriptHost {
     pub fn new(root: impl Into<PathBuf>) -> Self {
-        Self { root: root.into() }
+        Self {
+            root: root.into(),
+            memory_watches: MemoryWatches::default(),
+            variable_watches: VariableWatches::default(),
+            code_watches: Vec::new(),
+            section_changed_handlers: Vec::new(),
+            frame_handlers: Vec::new(),
+            frame_handler_start: 0,
+            call_arg_types: FnvHashMap::default(),
+        }
+    }
+
+    /// Argument types declared for a function called from rules.
+    /// Without a `ScriptHost` global, all arguments are strings.
+    pub fn call_arg_types(lua: &Lua, name: &str) -> mlua::Result<Vec<ArgType>> {
+        let Some(script_host) = lua.globals().get::<Option<AnyUserData>>("ScriptHost")? else {
+            return Ok(Vec::new());
+        };
+        let script_host = script_host.borrow::<Self>()?;
+
+        Ok(script_host
+            .call_arg_types
+            .get(name)
+            .cloned()
+            .unwrap_or_default())
     }
 }
 
@@ -48,6 +98,160 @@ impl UserData for ScriptHost {
             },
         );
 
+        methods.add_method_mut(
+            "AddMemoryWatch",
+            |_lua,
+             this,
+             (name, address, length, callback, interval): (
+                String,
+                u32,
+                usize,
+                Function,
+                Option<u64>,
+            )| {
+                let _span =
+                    debug_span!("ScriptHost::AddMemoryWatch", name, address, length).entered();
+                let interval = interval.map_or(DEFAULT_INTERVAL, Duration::from_millis);
+                let request = ReadRequest { address, length };
+
+                this.memory_watches
+                    .add(name.clone(), request, interval, callback);
+
+                Ok(name)
+            },
+        );
+
+        methods.add_method_mut("RemoveMemoryWatch", |_lua, this, name: String| {
+            let _span = debug_span!("ScriptHost::RemoveMemoryWatch", name).entered();
+
+            Ok(this.memory_watches.remove(&name))
+        });
+
+        methods.add_method_mut(
+            "AddVariableWatch",
+            |_lua,
+             this,
+             (name, variables, callback, interval): (
+                String,
+                Vec<String>,
+                Function,
+                Option<u64>,
+            )| {
+                let _span = debug_span!("ScriptHost::AddVariableWatch", name, ?variables).entered();
+                let interval = interval.map_or(Duration::ZERO, Duration::from_millis);
+
+                this.variable_watches
+                    .add(name.clone(), variables, interval, callback);
+
+                Ok(name)
+            },
+        );
+
+        methods.add_method_mut("RemoveVariableWatch", |_lua, this, name: String| {
+            let _span = debug_span!("ScriptHost::RemoveVariableWatch", name).entered();
+
+            Ok(this.variable_watches.remove(&name))
+        });
+
+        methods.add_method_mut(
+            "AddWatchForCode",
+            |_lua, this, (name, code, callback): (String, String, Function)| {
+                let _span = debug_span!("ScriptHost::AddWatchForCode", name, code).entered();
+
+                this.code_watches.retain(|watch| watch.name != name);
+                this.code_watches.push(CodeWatch {
+                    name: name.clone(),
+                    code,
+                    callback,
+                });
+
+                Ok(name)
+            },
+        );
+
+        methods.add_method_mut("RemoveWatchForCode", |_lua, this, name: String| {
+            let _span = debug_span!("ScriptHost::RemoveWatchForCode", name).entered();
+            let len = this.code_watches.len();
+
+            this.code_watches.retain(|watch| watch.name != name);
+
+            Ok(this.code_watches.len() != len)
+        });
+
+        methods.add_method_mut(
+            "AddOnLocationSectionChangedHandler",
+            |_lua, this, (name, callback): (String, Function)| {
+                let _span =
+                    debug_span!("ScriptHost::AddOnLocationSectionChangedHandler", name).entered();
+
+                this.section_changed_handlers
+                    .retain(|(handler_name, _)| *handler_name != name);
+                this.section_changed_handlers.push((name.clone(), callback));
+
+                Ok(name)
+            },
+        );
+
+        methods.add_method_mut(
+            "RemoveOnLocationSectionChangedHandler",
+            |_lua, this, name: String| {
+                let _span = debug_span!("ScriptHost::RemoveOnLocationSectionChangedHandler", name)
+                    .entered();
+                let len = this.section_changed_handlers.len();
+
+                this.section_changed_handlers
+                    .retain(|(handler_name, _)| *handler_name != name);
+
+                Ok(this.section_changed_handlers.len() != len)
+            },
+        );
+
+        methods.add_method_mut(
+            "AddOnFrameHandler",
+            |_lua, this, (name, callback): (String, Function)| {
+                let _span = debug_span!("ScriptHost::AddOnFrameHandler", name).entered();
+
+                this.frame_handlers
+                    .retain(|(handler_name, _)| *handler_name != name);
+                this.frame_handlers.push((name.clone(), callback));
+
+                Ok(name)
+            },
+        );
+
+        methods.add_method_mut("RemoveOnFrameHandler", |_lua, this, name: String| {
+            let _span = debug_span!("ScriptHost::RemoveOnFrameHandler", name).entered();
+            let len = this.frame_handlers.len();
+
+            this.frame_handlers
+                .retain(|(handler_name, _)| *handler_name != name);
+
+            Ok(this.frame_handlers.len() != len)
+        });
+
+        methods.add_method_mut(
+            "SetCallArgTypes",
+            |_lua, this, (name, arg_types): (String, Vec<ArgType>)| {
+                let _span = debug_span!("ScriptHost::SetCallArgTypes", name, ?arg_types).entered();
+
+                this.call_arg_types.insert(name, arg_types);
+
+                Ok(())
+            },
+        );
+
+        methods.add_method("CreateLuaItem", |lua, _this, ()| {
+            let _span = debug_span!("ScriptHost::CreateLuaItem").entered();
+            let item = lua.create_userdata(LuaItem::default())?;
+
+            lua.globals()
+                .get::<AnyUserData>("Tracker")?
+                .borrow_mut::<Tracker>()?
+                .add_lua_item(item.clone());
+
+            Ok(item)
+        });
+
         methods.add_meta_method("__index", |_, _, index: mlua::Value| -> mlua::Result<()> {
             let index = index.to_string()?;
 
@@ -57,3 +261,193 @@ impl UserData for ScriptHost {
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use pretty_assertions::assert_eq;
+
+    use crate::pack::api::tracker::Click;
+    use crate::pack::test_helpers::{eval, fixture_pack};
+    use crate::pack::Pack;
+
+    const BUDGET: Duration = Duration::from_millis(20);
+
+    fn click_sword(pack: &Pack) {
+        pack.api
+            .with_tracker_mut(|tracker| tracker.click_item(0, Click::Left))
+            .unwrap();
+        pack.api.dispatch_tracker_changes().unwrap();
+    }
+
+    #[test]
+    fn code_watches_are_called_for_matching_items() {
+        let pack = fixture_pack("watches");
+
+        click_sword(&pack);
+
+        assert_eq!(
+            eval::<Vec<String>>(&pack, "return code_calls"),
+            ["sword", "*"]
+        );
+
+        pack.api
+            .with_tracker_mut(|tracker| tracker.click_item(1, Click::Left))
+            .unwrap();
+        pack.api.dispatch_tracker_changes().unwrap();
+
+        assert_eq!(
+            eval::<Vec<String>>(&pack, "return code_calls"),
+            ["sword", "*", "*"]
+        );
+    }
+
+    #[test]
+    fn removed_code_watches_are_not_called() {
+        let pack = fixture_pack("watches");
+
+        assert!(eval::<bool>(
+            &pack,
+            r#"return ScriptHost:RemoveWatchForCode("sword")"#
+        ));
+        assert!(!eval::<bool>(
+            &pack,
+            r#"return ScriptHost:RemoveWatchForCode("sword")"#
+        ));
+
+        click_sword(&pack);
+
+        assert_eq!(eval::<Vec<String>>(&pack, "return code_calls"), ["*"]);
+    }
+
+    #[test]
+    fn section_changed_handlers_are_called() {
+        let pack = fixture_pack("watches");
+
+        pack.api.toggle_location_cleared("Cave").unwrap();
+        pack.api.dispatch_tracker_changes().unwrap();
+
+        assert_eq!(
+            eval::<Vec<String>>(&pack, "return section_calls"),
+            ["Cave/Front", "Cave/Back"]
+        );
+        assert_eq!(eval::<Vec<u32>>(&pack, "return section_chests"), [0, 0]);
+
+        assert!(eval::<bool>(
+            &pack,
+            r#"return ScriptHost:RemoveOnLocationSectionChangedHandler("sections")"#
+        ));
+
+        pack.api.toggle_location_cleared("Cave").unwrap();
+        pack.api.dispatch_tracker_changes().unwrap();
+
+        assert_eq!(eval::<Vec<String>>(&pack, "return section_calls").len(), 2);
+    }
+
+    #[test]
+    fn loading_state_dispatches_changes() {
+        let pack = fixture_pack("watches");
+        let loaded_pack = fixture_pack("watches");
+
+        click_sword(&pack);
+
+        let state = pack.api.with_tracker(|tracker| tracker.state()).unwrap();
+
+        loaded_pack
+            .api
+            .with_tracker_mut(|tracker| tracker.load_state(&state))
+            .unwrap();
+        loaded_pack.api.dispatch_tracker_changes().unwrap();
+
+        assert_eq!(
+            eval::<Vec<String>>(&loaded_pack, "return code_calls"),
+            ["sword", "*"]
+        );
+    }
+
+    #[test]
+    fn frame_handlers_receive_elapsed_time() {
+        let pack = fixture_pack("watches");
+
+        assert!(!pack
+            .api
+            .run_frame_handlers(Duration::from_millis(500), BUDGET)
+            .unwrap());
+
+        pack.api
+            .lua()
+            .load(
+                r#"
+                total_elapsed = 0
+
+                ScriptHost:AddOnFrameHandler("timer", function(elapsed)
+                    total_elapsed = total_elapsed + elapsed
+                end)
+                "#,
+            )
+            .exec()
+            .unwrap();
+
+        for _ in 0..2 {
+            assert!(pack
+                .api
+                .run_frame_handlers(Duration::from_millis(500), BUDGET)
+                .unwrap());
+        }
+
+        assert_eq!(eval::<f64>(&pack, "return total_elapsed"), 1.0);
+
+        assert!(eval::<bool>(
+            &pack,
+            r#"return ScriptHost:RemoveOnFrameHandler("timer")"#
+        ));
+        assert!(!pack
+            .api
+            .run_frame_handlers(Duration::from_millis(500), BUDGET)
+            .unwrap());
+    }
+
+    #[test]
+    fn frame_handlers_are_interrupted_after_budget() {
+        let pack = fixture_pack("watches");
+
+        pack.api
+            .lua()
+            .load(
+                r#"
+                later_called = false
+
+                ScriptHost:AddOnFrameHandler("stuck", function()
+                    while true do end
+                end)
+                ScriptHost:AddOnFrameHandler("later", function()
+                    later_called = true
+                end)
+                "#,
+            )
+            .exec()
+            .unwrap();
+
+        let start = Instant::now();
+
+        assert!(pack.api.run_frame_handlers(Duration::ZERO, BUDGET).unwrap());
+
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert!(!eval::<bool>(&pack, "return later_called"));
+
+        // The skipped handler goes first in the next frame
+        assert!(pack.api.run_frame_handlers(Duration::ZERO, BUDGET).unwrap());
+
+        assert!(eval::<bool>(&pack, "return later_called"));
+
+        // Other code is not interrupted after the frame
+        assert_eq!(
+            eval::<u32>(
+                &pack,
+                "local n = 0 for i = 1, 100000 do n = n + 1 end return n"
+            ),
+            100000
+        );
+    }
+}
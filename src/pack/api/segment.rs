@@ -0,0 +1,42 @@
+use mlua::{UserData, UserDataFields, UserDataMethods};
+use tracing::error;
+
+use crate::autotracker::Segment;
+
+impl UserData for Segment {
+    fn add_fields<F: UserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("Address", |_, this| Ok(this.address));
+        fields.add_field_method_get("Size", |_, this| Ok(this.data.len()));
+    }
+
+    fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        for (name, size) in [
+            ("ReadU8", 1),
+            ("ReadU16", 2),
+            ("ReadU24", 3),
+            ("ReadU32", 4),
+        ] {
+            methods.add_method(name, move |_, this, address: u32| {
+                // Like PopTracker, reads outside of the segment return 0.
+                let value = this.read(address, size).unwrap_or_else(|| {
+                    error!(
+                        "{name}: address {address:#x} is outside of segment {:#x}..{:#x}",
+                        this.address,
+                        this.address as usize + this.data.len()
+                    );
+                    0
+                });
+
+                Ok(value)
+            });
+        }
+
+        methods.add_meta_method("__index", |_, _, index: mlua::Value| -> mlua::Result<()> {
+            let index = index.to_string()?;
+
+            Err(mlua::Error::runtime(format!(
+                "`Segment.{index}` does not exist"
+            )))
+        });
+    }
+}
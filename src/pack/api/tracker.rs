@@ -1,16 +1,22 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::{fs, iter};
 
 use eyre::{eyre, Context};
-use mlua::{Lua, UserData, UserDataFields, UserDataMethods};
+use fnv::FnvHashMap;
+use indexmap::IndexMap;
+use mlua::{AnyUserData, IntoLua, Lua, UserData, UserDataFields, UserDataMethods, Value};
+use serde::Deserialize;
 use tracing::{debug, debug_span, error, instrument, warn};
 
-use crate::pack::rule::{Call, Rule};
+use crate::pack::rule::{Call, Reference, Rule};
 use crate::pack::VariantUID;
 use crate::util::deserialize_hjson;
 
 mod item;
-pub use item::Item;
+pub use item::{Display, Item};
+
+pub mod layout;
+pub use layout::Layout;
 
 mod map;
 pub use map::{LocationShape, Map};
@@ -18,21 +24,52 @@ pub use map::{LocationShape, Map};
 mod location;
 pub use location::Location;
 
+mod lua_item;
+pub use lua_item::{LuaItem, LuaItemHandle};
+
 mod map_location;
 pub use map_location::MapLocation;
 
+mod object;
+pub use object::{ItemObject, SectionObject};
+
 mod section;
 pub use section::Section;
 
+pub mod state;
+use state::ItemState;
+pub use state::TrackerState;
+
 mod stateful_item;
-pub use stateful_item::StatefulItem;
+pub use stateful_item::{Click, StatefulItem};
+
+/// State change of the tracker that pack scripts may want to react to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// State of the item with the index changed.
+    Item(usize),
+    /// State of the section with the id changed.
+    Section(String),
+}
+
+/// What a [`Reference`] refers to.
+#[derive(Debug, Clone, Copy)]
+pub enum ReferenceTarget<'a> {
+    Location(&'a Location),
+    Section(&'a Location, &'a Section),
+}
 
 pub struct Tracker {
     root: PathBuf,
     maps: Vec<Map>,
     locations: Vec<Location>,
     items: Vec<StatefulItem>,
+    layouts: FnvHashMap<String, Layout>,
+    pinned_locations: Vec<String>,
+    notes: IndexMap<String, String>,
     variant_uid: VariantUID,
+    /// Changes not yet dispatched to the handlers of the pack.
+    changes: Vec<Change>,
 }
 
 impl Tracker {
@@ -42,14 +79,76 @@ impl Tracker {
             maps: Vec::new(),
             locations: Vec::new(),
             items: Vec::new(),
+            layouts: FnvHashMap::default(),
+            pinned_locations: Vec::new(),
+            notes: IndexMap::new(),
             variant_uid: variant_uid.clone(),
+            changes: Vec::new(),
         }
     }
 
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
     pub fn maps(&self) -> &[Map] {
         &self.maps
     }
 
+    pub fn find_map(&self, name: &str) -> Option<&Map> {
+        self.maps.iter().find(|map| map.name == name)
+    }
+
+    pub fn items(&self) -> &[StatefulItem] {
+        &self.items
+    }
+
+    /// Finds the first item that can provide the code and returns it with its index.
+    pub fn find_item_for_code(&self, code: &str) -> Option<(usize, &StatefulItem)> {
+        self.items
+            .iter()
+            .enumerate()
+            .find(|(_, item)| item.has_code(code))
+    }
+
+    /// Changes an item and records a change if its state changed.
+    pub fn update_item<R>(
+        &mut self,
+        index: usize,
+        f: impl FnOnce(&mut StatefulItem) -> R,
+    ) -> Option<R> {
+        let item = self.items.get_mut(index)?;
+        let old_state = item.state();
+        let result = f(item);
+
+        if item.state() != old_state {
+            self.push_change(Change::Item(index));
+        }
+
+        Some(result)
+    }
+
+    pub fn layout(&self, key: &str) -> Option<&Layout> {
+        self.layouts.get(key)
+    }
+
+    /// Returns `true` if the item state changed.
+    /// Lua items are clicked with [`Api::click_item`](crate::pack::api::Api::click_item).
+    pub fn click_item(&mut self, index: usize, click: Click) -> bool {
+        let Some(item) = self.items.get_mut(index) else {
+            error!("unknown item index {index}");
+            return false;
+        };
+
+        let changed = item.click(click);
+
+        if changed {
+            self.push_change(Change::Item(index));
+        }
+
+        changed
+    }
+
     pub fn locations(&self) -> &[Location] {
         &self.locations
     }
@@ -60,6 +159,417 @@ impl Tracker {
             .flat_map(|location| iter::once(location).chain(location.child_locations_recursive()))
     }
 
+    pub fn find_location(&self, name: &str) -> Option<&Location> {
+        self.locations_recursive()
+            .find(|location| location.name == name)
+    }
+
+    pub fn location(&self, id: &str) -> Option<&Location> {
+        self.locations_recursive()
+            .find(|location| location.id == id)
+    }
+
+    /// Finds a location by the names of the location and its parents.
+    /// The path may start at any location, exact ids are preferred.
+    pub fn find_location_by_path(&self, path: &[String]) -> Option<&Location> {
+        let id = path.join("/");
+        let suffix = format!("/{id}");
+
+        self.location(&id).or_else(|| {
+            self.locations_recursive()
+                .find(|location| location.id.ends_with(&suffix))
+        })
+    }
+
+    /// Resolves a reference to a section, or to a location if no such section exists.
+    /// The last segment of references with a section may name a child location instead.
+    pub fn resolve_reference(&self, reference: &Reference) -> Option<ReferenceTarget<'_>> {
+        let Some(section_name) = &reference.section else {
+            return self
+                .find_location_by_path(&reference.location)
+                .map(ReferenceTarget::Location);
+        };
+
+        let section = self
+            .find_location_by_path(&reference.location)
+            .and_then(|location| {
+                location
+                    .sections
+                    .iter()
+                    .find(|section| section.name.as_ref() == Some(section_name))
+                    .map(|section| ReferenceTarget::Section(location, section))
+            });
+
+        section.or_else(|| {
+            let path = [reference.location.as_slice(), &[section_name.clone()]].concat();
+
+            self.find_location_by_path(&path)
+                .map(ReferenceTarget::Location)
+        })
+    }
+
+    /// References in the rules of all locations that don't resolve, with the ids of the
+    /// locations using them.
+    pub fn unresolved_references(&self) -> Vec<(String, Reference)> {
+        let mut unresolved = Vec::new();
+
+        for location in self.locations_recursive() {
+            let section_rules = location
+                .sections
+                .iter()
+                .flat_map(|section| [&section.access_rules, &section.visibility_rules]);
+            let map_location_rules = location.map_locations.iter().flat_map(|map_location| {
+                [
+                    &map_location.restrict_visibility_rules,
+                    &map_location.force_invisibility_rules,
+                ]
+            });
+            let rules = [&location.access_rules, &location.visibility_rules]
+                .into_iter()
+                .chain(section_rules)
+                .chain(map_location_rules)
+                .flatten();
+
+            for reference in rules.flat_map(Rule::references) {
+                if self.resolve_reference(reference).is_none() {
+                    unresolved.push((location.id.clone(), reference.clone()));
+                }
+            }
+        }
+
+        unresolved
+    }
+
+    pub fn parent_location(&self, location: &Location) -> Option<&Location> {
+        self.location(location.parent_id()?)
+    }
+
+    pub fn location_mut(&mut self, id: &str) -> Option<&mut Location> {
+        let mut pending = self.locations.iter_mut().rev().collect::<Vec<_>>();
+
+        while let Some(location) = pending.pop() {
+            if location.id == id {
+                return Some(location);
+            }
+
+            pending.extend(location.children.iter_mut().rev());
+        }
+
+        None
+    }
+
+    /// Finds a section by its id, the id of its location and its name joined by `/`.
+    pub fn section(&self, id: &str) -> Option<(&Location, &Section)> {
+        self.locations_recursive().find_map(|location| {
+            location
+                .sections
+                .iter()
+                .find(|section| section_id(&location.id, section) == id)
+                .map(|section| (location, section))
+        })
+    }
+
+    /// Changes a section and records a change if its state changed.
+    pub fn update_section<R>(&mut self, id: &str, f: impl FnOnce(&mut Section) -> R) -> Option<R> {
+        let (location_id, section_index) = self.locations_recursive().find_map(|location| {
+            location
+                .sections
+                .iter()
+                .position(|section| section_id(&location.id, section) == id)
+                .map(|section_index| (location.id.clone(), section_index))
+        })?;
+        let section = self
+            .location_mut(&location_id)?
+            .sections
+            .get_mut(section_index)?;
+        let old_state = section.state();
+        let result = f(section);
+
+        if section.state() != old_state {
+            self.push_change(Change::Section(id.to_owned()));
+        }
+
+        Some(result)
+    }
+
+    fn for_each_location_mut(&mut self, mut f: impl FnMut(&mut Location)) {
+        let mut pending = self.locations.iter_mut().rev().collect::<Vec<_>>();
+
+        while let Some(location) = pending.pop() {
+            f(location);
+            pending.extend(location.children.iter_mut().rev());
+        }
+    }
+
+    /// Clears the sections and collects their hosted items, or restores the sections and
+    /// uncollects their hosted items.
+    /// The callbacks of Lua items may access the tracker, so hosted Lua items are returned with
+    /// their codes instead, see [`Api::toggle_location_cleared`].
+    ///
+    /// [`Api::toggle_location_cleared`]: crate::pack::api::Api::toggle_location_cleared
+    pub fn set_sections_cleared(
+        &mut self,
+        section_ids: &[String],
+        cleared: bool,
+    ) -> Vec<(LuaItemHandle, String)> {
+        let hosted_items = section_ids
+            .iter()
+            .filter_map(|id| {
+                let code = self.section(id)?.1.hosted_item.clone()?;
+                let (index, _) = self.find_item_for_code(&code)?;
+
+                Some((index, code))
+            })
+            .collect::<Vec<_>>();
+
+        for id in section_ids {
+            if self
+                .update_section(id, |section| section.set_cleared(cleared))
+                .is_none()
+            {
+                error!("unknown section `{id}`");
+            }
+        }
+
+        let mut lua_items = Vec::new();
+
+        for (index, code) in hosted_items {
+            match self.items[index].lua_item() {
+                Some(lua_item) => lua_items.push((lua_item.clone(), code)),
+                None => {
+                    self.update_item(index, |item| item.set_collected(cleared));
+                }
+            }
+        }
+
+        lua_items
+    }
+
+    /// Whether all chests of the section are cleared and its hosted item is collected,
+    /// see [`StatefulItem::is_collected`].
+    /// Sections with unknown hosted items are never cleared, see
+    /// [`unknown_hosted_items`](Self::unknown_hosted_items).
+    pub fn is_section_cleared(&self, section: &Section) -> bool {
+        if !section.chests_cleared() {
+            return false;
+        }
+
+        let Some(code) = &section.hosted_item else {
+            return true;
+        };
+
+        self.find_item_for_code(code)
+            .is_some_and(|(_, item)| item.is_collected(code))
+    }
+
+    /// Codes of hosted items no item provides, with the ids of the sections hosting them.
+    pub fn unknown_hosted_items(&self) -> Vec<(String, String)> {
+        self.locations_recursive()
+            .flat_map(|location| {
+                location.sections.iter().filter_map(|section| {
+                    let code = section.hosted_item.as_ref()?;
+
+                    self.find_item_for_code(code)
+                        .is_none()
+                        .then(|| (section_id(&location.id, section), code.clone()))
+                })
+            })
+            .collect()
+    }
+
+    /// Clears a chest of the section on left click and restores one on right click.
+    pub fn click_section_chest(&mut self, id: &str, click: Click) {
+        let found = self.update_section(id, |section| match click {
+            Click::Left => section.clear_chest(),
+            Click::Right => section.restore_chest(),
+            Click::Middle => false,
+        });
+
+        if found.is_none() {
+            error!("unknown section `{id}`");
+        }
+    }
+
+    pub fn add_items(&mut self, items: impl IntoIterator<Item = Item>) {
+        let items = items.into_iter().map(StatefulItem::new);
+
+        self.items.extend(items);
+    }
+
+    /// Adds an item implemented by the pack with a [`LuaItem`] userdata.
+    pub fn add_lua_item(&mut self, item: AnyUserData) {
+        self.items.push(StatefulItem::lua(item));
+    }
+
+    pub fn add_locations(&mut self, locations: impl IntoIterator<Item = Location>) {
+        let locations = locations.into_iter().map(|mut location| {
+            location.assign_ids(None);
+            location
+        });
+
+        self.locations.extend(locations);
+    }
+
+    /// Ids of pinned locations, most recently pinned first.
+    pub fn pinned_locations(&self) -> &[String] {
+        &self.pinned_locations
+    }
+
+    pub fn is_location_pinned(&self, id: &str) -> bool {
+        self.pinned_locations
+            .iter()
+            .any(|pinned_id| pinned_id == id)
+    }
+
+    pub fn toggle_location_pinned(&mut self, id: &str) {
+        if self.is_location_pinned(id) {
+            self.pinned_locations.retain(|pinned_id| pinned_id != id);
+        } else {
+            self.pinned_locations.insert(0, id.to_owned());
+        }
+    }
+
+    pub fn location_note(&self, id: &str) -> &str {
+        self.notes.get(id).map(String::as_str).unwrap_or_default()
+    }
+
+    /// Sets the note of a location. Empty notes are removed.
+    pub fn set_location_note(&mut self, id: &str, note: String) {
+        if note.is_empty() {
+            self.notes.shift_remove(id);
+        } else {
+            self.notes.insert(id.to_owned(), note);
+        }
+    }
+
+    pub fn variant_uid(&self) -> &VariantUID {
+        &self.variant_uid
+    }
+
+    /// Lua items with their ids in saved states.
+    pub fn lua_items(&self) -> Vec<(String, LuaItemHandle)> {
+        self.items
+            .iter()
+            .enumerate()
+            .filter_map(|(index, item)| Some((item_id(index), item.lua_item()?.clone())))
+            .collect()
+    }
+
+    /// State of the tracker without the states saved by Lua items,
+    /// see [`Api::tracker_state`](crate::pack::api::Api::tracker_state).
+    pub fn state(&self) -> TrackerState {
+        let items = self
+            .items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| {
+                let state = serde_json::to_value(item.state()).expect("item states are JSON");
+
+                (item_id(index), state)
+            })
+            .collect();
+
+        let sections = self
+            .locations_recursive()
+            .flat_map(|location| {
+                location
+                    .sections
+                    .iter()
+                    .map(|section| (section_id(&location.id, section), section.state()))
+            })
+            .collect();
+
+        TrackerState {
+            items,
+            sections,
+            pins: self.pinned_locations.clone(),
+            notes: self.notes.clone(),
+        }
+    }
+
+    /// Restores a previously saved state, except for the states of Lua items,
+    /// see [`Api::load_tracker_state`](crate::pack::api::Api::load_tracker_state).
+    /// Items and sections missing from the state keep their current state.
+    pub fn load_state(&mut self, state: &TrackerState) {
+        let mut changes = Vec::new();
+
+        for (index, item) in self.items.iter_mut().enumerate() {
+            let id = item_id(index);
+            let Some(item_state) = state.items.get(&id) else {
+                continue;
+            };
+
+            if item.lua_item().is_some() {
+                continue;
+            }
+
+            let item_state = match ItemState::deserialize(item_state) {
+                Ok(item_state) => item_state,
+                Err(err) => {
+                    warn!("invalid state of item `{id}`: {err}");
+                    continue;
+                }
+            };
+            let old_state = item.state();
+
+            item.load_state(&item_state);
+
+            if item.state() != old_state {
+                changes.push(Change::Item(index));
+            }
+        }
+
+        self.for_each_location_mut(|location| {
+            for section in &mut location.sections {
+                let id = section_id(&location.id, section);
+
+                if let Some(section_state) = state.sections.get(&id) {
+                    let old_state = section.state();
+
+                    section.load_state(section_state);
+
+                    if section.state() != old_state {
+                        changes.push(Change::Section(id));
+                    }
+                }
+            }
+        });
+
+        for change in changes {
+            self.push_change(change);
+        }
+
+        self.pinned_locations = state.pins.clone();
+        self.notes = state.notes.clone();
+    }
+
+    fn push_change(&mut self, change: Change) {
+        if !self.changes.contains(&change) {
+            self.changes.push(change);
+        }
+    }
+
+    /// Takes the changes since the last call, in the order they happened.
+    /// Lua items change without the tracker noticing, so their changes come last.
+    pub fn take_changes(&mut self) -> Vec<Change> {
+        let lua_item_changes = self
+            .items
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, item)| {
+                let changed = item.lua_item_mut()?.take_changed();
+
+                changed.then_some(Change::Item(index))
+            })
+            .collect::<Vec<_>>();
+
+        for change in lua_item_changes {
+            self.push_change(change);
+        }
+
+        std::mem::take(&mut self.changes)
+    }
+
     #[instrument(level = "error", skip(self))]
     pub fn provider_count_for_code(&self, lua: &Lua, code: &str) -> i32 {
         let rule = match code.parse::<Rule>() {
@@ -72,7 +582,7 @@ impl Tracker {
 
         match rule {
             Rule::Call(call) => self.provider_count_for_call(lua, &call),
-            Rule::Item(name) => self.provider_count_for_item(&name),
+            Rule::Item(item) => self.provider_count_for_item(&item.code),
             _ => {
                 error!("invalid code (only lua calls and item code allowed)");
                 0
@@ -82,8 +592,18 @@ impl Tracker {
 
     #[instrument(level = "error", skip(self))]
     pub fn provider_count_for_call(&self, lua: &Lua, call: &Call) -> i32 {
-        match call.exec::<i32>(lua) {
-            Ok(count) => count,
+        match call.exec::<Value>(lua) {
+            Ok(Value::Boolean(provided)) => provided as i32,
+            Ok(Value::Integer(count)) => count as i32,
+            Ok(Value::Number(count)) => count as i32,
+            Ok(value) => {
+                error!(
+                    "`{}` returned {} instead of a count",
+                    call.name,
+                    value.type_name()
+                );
+                0
+            }
             Err(err) => {
                 error!("failed to call `{}`: {err:?}", call.name);
                 0
@@ -103,6 +623,20 @@ impl Tracker {
     }
 }
 
+/// Id of an item in saved states. Like in PopTracker, this is its 1-based position in the
+/// order the pack added the items.
+pub fn item_id(index: usize) -> String {
+    (index + 1).to_string()
+}
+
+/// Id of a section, the id of its location and its name joined by `/`.
+pub fn section_id(location_id: &str, section: &Section) -> String {
+    format!(
+        "{location_id}/{}",
+        section.name.as_deref().unwrap_or_default()
+    )
+}
+
 impl Drop for Tracker {
     fn drop(&mut self) {
         debug!("Dropping Tracker");
@@ -137,9 +671,7 @@ impl UserData for Tracker {
                 .with_context(|| eyre!("failed to parse items json at {items_path:?}"))
                 .map_err(|err| mlua::Error::runtime(format!("{err:?}")))?;
 
-            let mut items = items.into_iter().map(StatefulItem::new).collect::<Vec<_>>();
-
-            this.items.append(&mut items);
+            this.add_items(items);
 
             Ok(())
         });
@@ -147,22 +679,54 @@ impl UserData for Tracker {
         methods.add_method_mut("AddLocations", |_, this, locations_path: String| {
             let locations_path = this.root.join(locations_path);
             let locations = fs::read_to_string(&locations_path)?;
-            let mut locations = deserialize_hjson::<Vec<Location>>(&locations)
+            let locations = deserialize_hjson::<Vec<Location>>(&locations)
                 .with_context(|| eyre!("failed to parse locations json at {locations_path:?}"))
                 .map_err(|err| mlua::Error::runtime(format!("{err:?}")))?;
 
-            this.locations.append(&mut locations);
+            this.add_locations(locations);
 
             Ok(())
         });
 
-        methods.add_method_mut("AddLayouts", |_, _tehis, _layouts_path: String| {
+        methods.add_method_mut("AddLayouts", |_, this, layouts_path: String| {
             let _span = debug_span!("Tracker::AddLayouts").entered();
-            warn!("TODO: implement");
+            let layouts_path = this.root.join(layouts_path);
+            let layouts = fs::read_to_string(&layouts_path)?;
+            let layouts = deserialize_hjson::<FnvHashMap<String, Layout>>(&layouts)
+                .with_context(|| eyre!("failed to parse layouts json at {layouts_path:?}"))
+                .map_err(|err| mlua::Error::runtime(format!("{err:?}")))?;
+
+            this.layouts.extend(layouts);
 
             Ok(())
         });
 
+        methods.add_method("FindObjectForCode", |lua, this, code: String| {
+            let _span = debug_span!("Tracker::FindObjectForCode", code).entered();
+
+            if let Some(id) = code.strip_prefix('@') {
+                if this.section(id).is_none() {
+                    error!("no section `{id}` found");
+                    return Ok(Value::Nil);
+                }
+
+                return SectionObject { id: id.to_owned() }.into_lua(lua);
+            }
+
+            match this.find_item_for_code(&code) {
+                Some((index, item)) => match item.lua_item() {
+                    // Lua items are returned as they are, so packs can access their callbacks
+                    Some(lua_item) => lua_item.userdata().clone().into_lua(lua),
+                    None => ItemObject { index }.into_lua(lua),
+                },
+                None => Ok(Value::Nil),
+            }
+        });
+
+        methods.add_method("ProviderCountForCode", |lua, this, code: String| {
+            Ok(this.provider_count_for_code(lua, &code))
+        });
+
         methods.add_meta_method("__index", |_, _, index: mlua::Value| -> mlua::Result<()> {
             let index = index.to_string()?;
 
@@ -172,3 +736,184 @@ impl UserData for Tracker {
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::{section_id, Click, Item, Location, Tracker};
+    use crate::pack::VariantUID;
+    use crate::util::deserialize_hjson;
+
+    fn tracker_with_items(items: &str) -> Tracker {
+        let mut tracker = Tracker::new("", &VariantUID::from("standard"));
+
+        tracker.add_items(deserialize_hjson::<Vec<Item>>(items).unwrap());
+
+        tracker
+    }
+
+    #[test]
+    fn item_states_are_keyed_by_id() {
+        let items = r#"[
+            { type: "toggle", name: "Lamp", codes: "lamp", img: "lamp.png" },
+            { type: "toggle", name: "Bottle", codes: "bottle", img: "bottle.png" },
+            { type: "toggle", name: "Bottle", codes: "bottle", img: "bottle.png" },
+            { type: "toggle", name: "Boots", img: "boots.png" },
+        ]"#;
+        let mut tracker = tracker_with_items(items);
+
+        for index in [0, 2, 3] {
+            tracker.click_item(index, Click::Left);
+        }
+
+        let state = tracker.state();
+
+        assert_eq!(state.items.keys().collect::<Vec<_>>(), ["1", "2", "3", "4"]);
+        assert_eq!(
+            state.items["1"],
+            json!({ "stage1": 1, "stage2": 0, "count": 0 })
+        );
+
+        let mut loaded_tracker = tracker_with_items(items);
+
+        loaded_tracker.load_state(&state);
+
+        let active = loaded_tracker
+            .items()
+            .iter()
+            .map(|item| item.is_active())
+            .collect::<Vec<_>>();
+
+        assert_eq!(active, [true, false, true, true]);
+    }
+
+    #[test]
+    fn sections_hosting_uncollected_items_are_not_cleared() {
+        let mut tracker = tracker_with_items(
+            r#"[
+                { type: "static", name: "Triforce", codes: "triforce", img: "triforce.png" },
+                {
+                    type: "progressive",
+                    name: "Bow",
+                    codes: "bow",
+                    allow_disabled: false,
+                    stages: [{ img: "bow.png" }, { img: "silver_bow.png" }],
+                },
+                { type: "consumable", name: "Bombs", codes: "bombs", img: "bombs.png" },
+            ]"#,
+        );
+        let locations = deserialize_hjson::<Vec<Location>>(
+            r#"[{
+                name: "Dungeon",
+                sections: [
+                    { name: "Triforce", hosted_item: "triforce" },
+                    { name: "Bow", hosted_item: "bow" },
+                    { name: "Bombs", hosted_item: "bombs" },
+                ],
+            }]"#,
+        )
+        .unwrap();
+
+        tracker.add_locations(locations);
+
+        let cleared = |tracker: &Tracker| {
+            tracker.locations()[0]
+                .sections
+                .iter()
+                .map(|section| tracker.is_section_cleared(section))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(cleared(&tracker), [false, false, false]);
+
+        for index in 0..3 {
+            tracker.click_item(index, Click::Left);
+        }
+
+        assert_eq!(cleared(&tracker), [false, true, true]);
+    }
+
+    #[test]
+    fn hosted_items_are_collected_with_their_sections() {
+        let mut tracker = tracker_with_items(
+            r#"[
+                {
+                    type: "progressive",
+                    name: "Bow",
+                    codes: "bow",
+                    allow_disabled: false,
+                    stages: [{ img: "bow.png" }, { img: "silver_bow.png" }],
+                },
+                {
+                    type: "progressive",
+                    name: "Sword",
+                    codes: "sword",
+                    stages: [{ img: "sword1.png" }, { img: "sword2.png" }],
+                },
+                { type: "consumable", name: "Bombs", codes: "bombs", img: "bombs.png" },
+                {
+                    type: "progressive_toggle",
+                    name: "Lantern",
+                    codes: "lantern",
+                    stages: [{ img: "lantern.png" }, { img: "lit_lantern.png" }],
+                },
+                {
+                    type: "composite_toggle",
+                    name: "Medallions",
+                    codes: "medallions",
+                    item_left: "bombos",
+                    item_right: "ether",
+                    images: [
+                        { left: false, right: false, img: "none.png" },
+                        { left: true, right: false, img: "left.png" },
+                        { left: false, right: true, img: "right.png" },
+                        { left: true, right: true, img: "both.png" },
+                    ],
+                },
+                { type: "static", name: "Triforce", codes: "triforce", img: "triforce.png" },
+            ]"#,
+        );
+        let locations = deserialize_hjson::<Vec<Location>>(
+            r#"[{
+                name: "Dungeon",
+                sections: [
+                    { name: "Bow", hosted_item: "bow" },
+                    { name: "Sword", hosted_item: "sword" },
+                    { name: "Bombs", hosted_item: "bombs" },
+                    { name: "Lantern", hosted_item: "lantern" },
+                    { name: "Medallions", hosted_item: "medallions" },
+                    { name: "Triforce", hosted_item: "triforce" },
+                ],
+            }]"#,
+        )
+        .unwrap();
+
+        tracker.add_locations(locations);
+
+        let section_ids = tracker.locations()[0]
+            .sections
+            .iter()
+            .map(|section| section_id("Dungeon", section))
+            .collect::<Vec<_>>();
+        let cleared = |tracker: &Tracker| {
+            tracker.locations()[0]
+                .sections
+                .iter()
+                .map(|section| tracker.is_section_cleared(section))
+                .collect::<Vec<_>>()
+        };
+
+        tracker.set_sections_cleared(&section_ids, true);
+
+        // Static items are never collected
+        assert_eq!(cleared(&tracker), [true, true, true, true, true, false]);
+        assert_eq!(tracker.items()[2].count(), Some(1));
+
+        tracker.set_sections_cleared(&section_ids, false);
+
+        assert_eq!(cleared(&tracker), [false; 6]);
+        assert_eq!(tracker.items()[0].current_stage(), 0);
+    }
+}
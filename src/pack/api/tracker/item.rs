@@ -15,7 +15,7 @@ pub struct Item {
     pub variant: Variant,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Common {
     #[serde(default)]
     pub name: String,
@@ -102,7 +102,8 @@ pub struct Consumable {
     #[serde(default, deserialize_with = "value_or_string")]
     pub min_quantity: i32,
     /// Maximum quantity of the consumable. Inclusive.
-    #[serde(default, deserialize_with = "value_or_string")]
+    /// Negative values mean unlimited.
+    #[serde(default = "const_i32::<-1>", deserialize_with = "value_or_string")]
     pub max_quantity: i32,
     /// Amount to increase the quantity by on left-click.
     #[serde(default = "const_i32::<1>", deserialize_with = "value_or_string")]
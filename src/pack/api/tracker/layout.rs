@@ -0,0 +1,410 @@
+use hex_color::HexColor;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::util::{one_or_many, option_value_or_string};
+
+/// Layout used by the main window.
+pub const DEFAULT_LAYOUT: &str = "tracker_default";
+/// Layout used by the main window when a horizontal layout is requested.
+pub const HORIZONTAL_LAYOUT: &str = "tracker_horizontal";
+/// Layout used by the broadcast window.
+pub const BROADCAST_LAYOUT: &str = "tracker_broadcast";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Layout {
+    #[serde(flatten)]
+    pub common: Common,
+    #[serde(flatten)]
+    pub variant: Variant,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Common {
+    pub background: Option<HexColor>,
+    /// Side to dock to if the parent is a dock.
+    pub dock: Option<DockSide>,
+    /// Space around the content.
+    pub margin: Option<Margin>,
+    #[serde(default, deserialize_with = "option_value_or_string")]
+    pub max_width: Option<u32>,
+    #[serde(default, deserialize_with = "option_value_or_string")]
+    pub max_height: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Variant {
+    Container(Container),
+    Dock(Dock),
+    Array(Array),
+    Tabbed(Tabbed),
+    Group(Group),
+    Map(Map),
+    ItemGrid(ItemGrid),
+    Item(Item),
+    RecentPins(RecentPins),
+    Layout(Reference),
+    /// Layout types that are not supported (yet).
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Container {
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub content: Vec<Layout>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Dock {
+    /// Docked content. Content without a dock side fills the remaining space.
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub content: Vec<Layout>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Array {
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub content: Vec<Layout>,
+    #[serde(default)]
+    pub orientation: Orientation,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Tabbed {
+    pub tabs: Vec<Tab>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Tab {
+    #[serde(default)]
+    pub title: String,
+    /// Image to display next to the title.
+    pub icon: Option<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub content: Vec<Layout>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Group {
+    #[serde(default)]
+    pub header: String,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub content: Vec<Layout>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Map {
+    /// Names of the maps to display.
+    #[serde(default)]
+    pub maps: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ItemGrid {
+    /// Item codes per row. Empty codes leave a gap.
+    #[serde(default)]
+    pub rows: Vec<Vec<String>>,
+    pub item_size: Option<Size>,
+    /// Space between items.
+    pub item_margin: Option<Size>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Item {
+    /// Code of the item to display.
+    pub item: String,
+    pub item_size: Option<Size>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecentPins {
+    #[serde(default)]
+    pub orientation: Orientation,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Reference {
+    /// Name of the referenced layout.
+    pub key: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DockSide {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Orientation {
+    #[default]
+    Horizontal,
+    Vertical,
+}
+
+/// Written as `"width,height"` or as a single number for both.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Size {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl<'de> Deserialize<'de> for Size {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match numbers(deserializer)?[..] {
+            [size] => Ok(Self {
+                width: size,
+                height: size,
+            }),
+            [width, height] => Ok(Self { width, height }),
+            _ => Err(de::Error::custom("expected one or two numbers")),
+        }
+    }
+}
+
+impl Serialize for Size {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let Self { width, height } = self;
+
+        serializer.collect_str(&format_args!("{width},{height}"))
+    }
+}
+
+/// Written as `"left,top,right,bottom"`, `"horizontal,vertical"`
+/// or as a single number for all sides.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Margin {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+impl<'de> Deserialize<'de> for Margin {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match numbers(deserializer)?[..] {
+            [margin] => Ok(Self {
+                left: margin,
+                top: margin,
+                right: margin,
+                bottom: margin,
+            }),
+            [horizontal, vertical] => Ok(Self {
+                left: horizontal,
+                top: vertical,
+                right: horizontal,
+                bottom: vertical,
+            }),
+            [left, top, right, bottom] => Ok(Self {
+                left,
+                top,
+                right,
+                bottom,
+            }),
+            _ => Err(de::Error::custom("expected one, two or four numbers")),
+        }
+    }
+}
+
+impl Serialize for Margin {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let Self {
+            left,
+            top,
+            right,
+            bottom,
+        } = self;
+
+        serializer.collect_str(&format_args!("{left},{top},{right},{bottom}"))
+    }
+}
+
+/// Deserializes a number or a string of comma seperated numbers.
+fn numbers<'de, D>(deserializer: D) -> Result<Vec<f32>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum NumberOrString {
+        Number(f32),
+        String(String),
+    }
+
+    match NumberOrString::deserialize(deserializer)? {
+        NumberOrString::Number(number) => Ok(vec![number]),
+        NumberOrString::String(numbers) => numbers
+            .split(',')
+            .map(|number| number.trim().parse::<f32>().map_err(de::Error::custom))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{DockSide, Layout, Margin, Orientation, Size, Variant};
+    use crate::util::deserialize_hjson;
+
+    #[test]
+    fn sizes() {
+        let sizes = deserialize_hjson::<Vec<Size>>(r#"[32, "32, 24"]"#).unwrap();
+
+        assert_eq!(
+            sizes,
+            [
+                Size {
+                    width: 32.0,
+                    height: 32.0,
+                },
+                Size {
+                    width: 32.0,
+                    height: 24.0,
+                },
+            ]
+        );
+        assert!(deserialize_hjson::<Vec<Size>>(r#"["1,2,3"]"#).is_err());
+        assert!(deserialize_hjson::<Vec<Size>>(r#"["wide"]"#).is_err());
+    }
+
+    #[test]
+    fn margins() {
+        let margins = deserialize_hjson::<Vec<Margin>>(r#"[4, "1,2", "1,2,3,4"]"#).unwrap();
+
+        assert_eq!(
+            margins,
+            [
+                Margin {
+                    left: 4.0,
+                    top: 4.0,
+                    right: 4.0,
+                    bottom: 4.0,
+                },
+                Margin {
+                    left: 1.0,
+                    top: 2.0,
+                    right: 1.0,
+                    bottom: 2.0,
+                },
+                Margin {
+                    left: 1.0,
+                    top: 2.0,
+                    right: 3.0,
+                    bottom: 4.0,
+                },
+            ]
+        );
+        assert!(deserialize_hjson::<Vec<Margin>>(r#"["1,2,3"]"#).is_err());
+    }
+
+    #[test]
+    fn variants() {
+        let layouts = deserialize_hjson::<Vec<Layout>>(
+            r#"[
+                { type: "array", orientation: "vertical", content: { type: "recentpins" } },
+                { type: "itemgrid", rows: [["sword", ""]], item_size: 24 },
+                { type: "item", item: "lamp" },
+                { type: "map", maps: ["World"] },
+                { type: "text", text: "Not supported" },
+            ]"#,
+        )
+        .unwrap();
+
+        let Variant::Array(array) = &layouts[0].variant else {
+            panic!("expected array: {:?}", layouts[0]);
+        };
+
+        assert_eq!(array.orientation, Orientation::Vertical);
+        assert!(matches!(
+            array.content[..],
+            [Layout {
+                variant: Variant::RecentPins(_),
+                ..
+            }]
+        ));
+
+        let Variant::ItemGrid(item_grid) = &layouts[1].variant else {
+            panic!("expected item grid: {:?}", layouts[1]);
+        };
+
+        assert_eq!(item_grid.rows, [["sword", ""]]);
+        assert_eq!(
+            item_grid.item_size,
+            Some(Size {
+                width: 24.0,
+                height: 24.0
+            })
+        );
+
+        assert!(matches!(&layouts[2].variant, Variant::Item(item) if item.item == "lamp"));
+        assert!(matches!(&layouts[3].variant, Variant::Map(map) if map.maps == ["World"]));
+        assert!(matches!(layouts[4].variant, Variant::Unknown));
+    }
+
+    #[test]
+    fn nested_references() {
+        let layout = deserialize_hjson::<Layout>(
+            r##"{
+                type: "dock",
+                background: "#102030",
+                max_width: "300",
+                content: [
+                    { type: "layout", key: "shared_items", dock: "left", margin: "2,4" },
+                    { type: "group", header: "Map", content: { type: "layout", key: "map" } },
+                ],
+            }"##,
+        )
+        .unwrap();
+
+        assert_eq!(layout.common.max_width, Some(300));
+        assert!(layout.common.background.is_some());
+
+        let Variant::Dock(dock) = &layout.variant else {
+            panic!("expected dock: {layout:?}");
+        };
+        let [items, group] = &dock.content[..] else {
+            panic!("expected two layouts: {dock:?}");
+        };
+
+        assert!(
+            matches!(&items.variant, Variant::Layout(reference) if reference.key == "shared_items")
+        );
+        assert_eq!(items.common.dock, Some(DockSide::Left));
+        assert_eq!(
+            items.common.margin,
+            Some(Margin {
+                left: 2.0,
+                top: 4.0,
+                right: 2.0,
+                bottom: 4.0
+            })
+        );
+
+        let Variant::Group(group) = &group.variant else {
+            panic!("expected group: {group:?}");
+        };
+
+        assert_eq!(group.header, "Map");
+        assert!(matches!(&group.content[..], [Layout {
+            variant: Variant::Layout(reference),
+            ..
+        }] if reference.key == "map"));
+    }
+}
@@ -7,11 +7,17 @@ use crate::pack::rule::Rule;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Location {
+    /// Names of the location and its parents, joined by `/`.
+    #[serde(skip)]
+    pub id: String,
     pub name: String,
     #[serde(default)]
     pub sections: Vec<Section>,
     #[serde(default)]
     pub access_rules: Vec<Rule>,
+    /// The location is hidden unless these rules are met.
+    #[serde(default)]
+    pub visibility_rules: Vec<Rule>,
     #[serde(default)]
     pub map_locations: Vec<MapLocation>,
     #[serde(default)]
@@ -19,6 +25,22 @@ pub struct Location {
 }
 
 impl Location {
+    pub(super) fn assign_ids(&mut self, parent_id: Option<&str>) {
+        self.id = match parent_id {
+            Some(parent_id) => format!("{parent_id}/{}", self.name),
+            None => self.name.clone(),
+        };
+
+        for child in &mut self.children {
+            child.assign_ids(Some(&self.id));
+        }
+    }
+
+    /// Id of the parent location, if this is a child location.
+    pub fn parent_id(&self) -> Option<&str> {
+        self.id.strip_suffix(self.name.as_str())?.strip_suffix('/')
+    }
+
     pub fn child_locations_recursive(&self) -> Box<dyn Iterator<Item = &Location> + '_> {
         Box::new(
             self.children.iter().flat_map(|location| {
@@ -0,0 +1,552 @@
+//! Items implemented by packs in Lua.
+//!
+//! The callbacks of a Lua item receive the item itself, so the item lives in its own userdata
+//! that the tracker only holds a handle to.
+//! Callbacks that may access the tracker are called without borrowing it, see
+//! [`Api::click_item`](crate::pack::api::Api::click_item).
+
+use mlua::{
+    AnyUserData, FromLua, Function, IntoLua, IntoLuaMulti, Lua, LuaSerdeExt, UserData,
+    UserDataFields, UserDataMethods, Value,
+};
+use tracing::error;
+
+use crate::pack::api::tracker::Click;
+
+/// Item created with `ScriptHost:CreateLuaItem`.
+#[derive(Debug)]
+pub struct LuaItem {
+    pub name: String,
+    pub icon: Option<String>,
+    /// State of the item, only used by the callbacks of the pack.
+    pub item_state: Value,
+    pub on_left_click: Option<Function>,
+    pub on_right_click: Option<Function>,
+    pub can_provide_code: Option<Function>,
+    pub provides_code: Option<Function>,
+    pub save: Option<Function>,
+    pub load: Option<Function>,
+    /// Incremented when the name, icon or item state changes.
+    /// Changes of the item state in place go unnoticed, see [`LuaItemHandle::click`].
+    revision: u64,
+}
+
+impl Default for LuaItem {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            icon: None,
+            item_state: Value::Nil,
+            on_left_click: None,
+            on_right_click: None,
+            can_provide_code: None,
+            provides_code: None,
+            save: None,
+            load: None,
+            revision: 0,
+        }
+    }
+}
+
+impl LuaItem {
+    pub fn set_name(&mut self, name: String) {
+        if self.name != name {
+            self.name = name;
+            self.revision += 1;
+        }
+    }
+
+    pub fn set_icon(&mut self, icon: Option<String>) {
+        if self.icon != icon {
+            self.icon = icon;
+            self.revision += 1;
+        }
+    }
+
+    /// Tables only compare equal to themselves, so every assignment counts as a change.
+    pub fn set_item_state(&mut self, item_state: Value) {
+        self.item_state = item_state;
+        self.revision += 1;
+    }
+}
+
+impl UserData for LuaItem {
+    fn add_fields<F: UserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("Name", |_, this| Ok(this.name.clone()));
+        fields.add_field_method_set("Name", |_, this, name: String| {
+            this.set_name(name);
+            Ok(())
+        });
+
+        fields.add_field_method_get("Icon", |_, this| Ok(this.icon.clone()));
+        fields.add_field_method_set("Icon", |_, this, icon: Option<String>| {
+            this.set_icon(icon);
+            Ok(())
+        });
+
+        fields.add_field_method_get("ItemState", |_, this| Ok(this.item_state.clone()));
+        fields.add_field_method_set("ItemState", |_, this, item_state: Value| {
+            this.set_item_state(item_state);
+            Ok(())
+        });
+
+        fields.add_field_method_get("OnLeftClickFunc", |_, this| Ok(this.on_left_click.clone()));
+        fields.add_field_method_set("OnLeftClickFunc", |_, this, callback: Option<Function>| {
+            this.on_left_click = callback;
+            Ok(())
+        });
+
+        fields.add_field_method_get("OnRightClickFunc", |_, this| {
+            Ok(this.on_right_click.clone())
+        });
+        fields.add_field_method_set("OnRightClickFunc", |_, this, callback: Option<Function>| {
+            this.on_right_click = callback;
+            Ok(())
+        });
+
+        fields.add_field_method_get("CanProvideCodeFunc", |_, this| {
+            Ok(this.can_provide_code.clone())
+        });
+        fields.add_field_method_set(
+            "CanProvideCodeFunc",
+            |_, this, callback: Option<Function>| {
+                this.can_provide_code = callback;
+                Ok(())
+            },
+        );
+
+        fields.add_field_method_get("ProvidesCodeFunc", |_, this| Ok(this.provides_code.clone()));
+        fields.add_field_method_set("ProvidesCodeFunc", |_, this, callback: Option<Function>| {
+            this.provides_code = callback;
+            Ok(())
+        });
+
+        fields.add_field_method_get("SaveFunc", |_, this| Ok(this.save.clone()));
+        fields.add_field_method_set("SaveFunc", |_, this, callback: Option<Function>| {
+            this.save = callback;
+            Ok(())
+        });
+
+        fields.add_field_method_get("LoadFunc", |_, this| Ok(this.load.clone()));
+        fields.add_field_method_set("LoadFunc", |_, this, callback: Option<Function>| {
+            this.load = callback;
+            Ok(())
+        });
+    }
+
+    fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        methods.add_meta_method("__index", |_, _, index: mlua::Value| -> mlua::Result<()> {
+            let index = index.to_string()?;
+
+            Err(mlua::Error::runtime(format!(
+                "`LuaItem.{index}` does not exist"
+            )))
+        });
+    }
+}
+
+/// Handle to a [`LuaItem`] that calls its callbacks.
+///
+/// Callbacks get the item as first argument. They are cloned out of the item before calling
+/// them, so they can change the item.
+/// Errors of callbacks are logged and treated like missing callbacks.
+#[derive(Debug, Clone)]
+pub struct LuaItemHandle {
+    item: AnyUserData,
+    /// Revision of the item when its changes were last taken.
+    seen_revision: u64,
+}
+
+impl LuaItemHandle {
+    pub fn new(item: AnyUserData) -> Self {
+        let mut handle = Self {
+            item,
+            seen_revision: 0,
+        };
+
+        handle.seen_revision = handle.revision();
+        handle
+    }
+
+    pub fn userdata(&self) -> &AnyUserData {
+        &self.item
+    }
+
+    fn with_item<R: Default>(&self, f: impl FnOnce(&LuaItem) -> R) -> R {
+        match self.item.borrow::<LuaItem>() {
+            Ok(item) => f(&item),
+            Err(err) => {
+                error!("failed to borrow Lua item: {err:?}");
+                R::default()
+            }
+        }
+    }
+
+    fn revision(&self) -> u64 {
+        self.with_item(|item| item.revision)
+    }
+
+    /// Returns `true` if the item changed since the last call.
+    pub fn take_changed(&mut self) -> bool {
+        let revision = self.revision();
+        let changed = revision != self.seen_revision;
+
+        self.seen_revision = revision;
+        changed
+    }
+
+    fn with_item_mut(&self, f: impl FnOnce(&mut LuaItem)) {
+        match self.item.borrow_mut::<LuaItem>() {
+            Ok(mut item) => f(&mut item),
+            Err(err) => error!("failed to borrow Lua item: {err:?}"),
+        }
+    }
+
+    /// Returns `None` if the item has no such callback or it failed.
+    fn call<R: FromLua>(
+        &self,
+        name: &str,
+        callback: impl FnOnce(&LuaItem) -> Option<Function>,
+        args: impl IntoLuaMulti,
+    ) -> Option<R> {
+        let callback = self.with_item(callback)?;
+
+        match callback.call::<R>(args) {
+            Ok(result) => Some(result),
+            Err(err) => {
+                error!("failed to call `{name}` of Lua item: {err:?}");
+                None
+            }
+        }
+    }
+
+    pub fn name(&self) -> String {
+        self.with_item(|item| item.name.clone())
+    }
+
+    pub fn set_name(&self, name: String) {
+        self.with_item_mut(|item| item.set_name(name));
+    }
+
+    pub fn icon(&self) -> Option<String> {
+        self.with_item(|item| item.icon.clone())
+    }
+
+    pub fn set_icon(&self, icon: Option<String>) {
+        self.with_item_mut(|item| item.set_icon(icon));
+    }
+
+    pub fn can_provide_code(&self, code: &str) -> bool {
+        self.call(
+            "CanProvideCodeFunc",
+            |item| item.can_provide_code.clone(),
+            (self.item.clone(), code),
+        )
+        .unwrap_or_default()
+    }
+
+    pub fn provides_code(&self, code: &str) -> i32 {
+        let count = self.call::<Value>(
+            "ProvidesCodeFunc",
+            |item| item.provides_code.clone(),
+            (self.item.clone(), code),
+        );
+
+        match count {
+            None | Some(Value::Nil) => 0,
+            Some(Value::Boolean(provided)) => provided as i32,
+            Some(Value::Integer(count)) => count as i32,
+            Some(Value::Number(count)) => count as i32,
+            Some(value) => {
+                error!(
+                    "`ProvidesCodeFunc` of Lua item returned {} instead of a count",
+                    value.type_name()
+                );
+                0
+            }
+        }
+    }
+
+    fn mark_changed(&self) {
+        self.with_item_mut(|item| item.revision += 1);
+    }
+
+    /// Returns `true` if the callback for the click changed the item.
+    /// Callbacks may change the item state in place, which is only noticed by comparing the
+    /// output of `SaveFunc`. Clicks of items without one always count as changes.
+    pub fn click(&self, click: Click) -> bool {
+        let name = match click {
+            Click::Left => "OnLeftClickFunc",
+            Click::Right => "OnRightClickFunc",
+            Click::Middle => return false,
+        };
+        let callback = move |item: &LuaItem| match click {
+            Click::Left => item.on_left_click.clone(),
+            Click::Right => item.on_right_click.clone(),
+            Click::Middle => None,
+        };
+
+        if self.with_item(callback).is_none() {
+            return false;
+        }
+
+        let revision = self.revision();
+        let old_state = self.save();
+
+        self.call::<()>(name, callback, self.item.clone());
+
+        let changed =
+            self.revision() != revision || old_state.is_none() || self.save() != old_state;
+
+        if changed {
+            self.mark_changed();
+        }
+
+        changed
+    }
+
+    /// State returned by the `SaveFunc` of the item.
+    pub fn save(&self) -> Option<serde_json::Value> {
+        self.call::<Json>("SaveFunc", |item| item.save.clone(), self.item.clone())
+            .map(|Json(state)| state)
+    }
+
+    /// Passes a state returned by [`save`](Self::save) to the `LoadFunc` of the item.
+    /// `LoadFunc` may change the item state in place, so loading always counts as a change.
+    pub fn load(&self, state: &serde_json::Value) {
+        let args = (self.item.clone(), Json(state.clone()));
+
+        self.call::<()>("LoadFunc", |item| item.load.clone(), args);
+        self.mark_changed();
+    }
+}
+
+/// Lua value converted from or to JSON.
+struct Json(serde_json::Value);
+
+impl FromLua for Json {
+    fn from_lua(value: Value, lua: &Lua) -> mlua::Result<Self> {
+        lua.from_value(value).map(Json)
+    }
+}
+
+impl IntoLua for Json {
+    fn into_lua(self, lua: &Lua) -> mlua::Result<Value> {
+        lua.to_value(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use serde_json::json;
+
+    use crate::pack::api::tracker::{Change, Click, Tracker};
+    use crate::pack::test_helpers::{eval, fixture_pack};
+    use crate::pack::Pack;
+
+    fn click_lantern(pack: &Pack, click: Click) -> bool {
+        pack.api.click_item(0, click).unwrap()
+    }
+
+    fn take_changes(pack: &Pack) -> Vec<Change> {
+        pack.api.with_tracker_mut(Tracker::take_changes).unwrap()
+    }
+
+    fn provider_counts(pack: &Pack) -> [i32; 2] {
+        pack.api
+            .with_tracker(|tracker| {
+                [
+                    tracker.provider_count_for_item("lantern"),
+                    tracker.provider_count_for_item("lit_lantern"),
+                ]
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn clicks_call_the_callbacks() {
+        let pack = fixture_pack("lua_items");
+
+        assert_eq!(provider_counts(&pack), [0, 0]);
+
+        click_lantern(&pack, Click::Left);
+        click_lantern(&pack, Click::Left);
+
+        assert_eq!(provider_counts(&pack), [1, 1]);
+
+        click_lantern(&pack, Click::Right);
+
+        assert_eq!(provider_counts(&pack), [1, 0]);
+        assert_eq!(
+            pack.api
+                .with_tracker(|tracker| tracker.items()[0].icon().unwrap().into_owned())
+                .unwrap(),
+            "images/lantern_1.png"
+        );
+    }
+
+    #[test]
+    fn collected_while_providing_code() {
+        let pack = fixture_pack("lua_items");
+        let is_collected = |pack: &Pack| {
+            pack.api
+                .with_tracker(|tracker| tracker.items()[0].is_collected("lantern"))
+                .unwrap()
+        };
+
+        assert!(!is_collected(&pack));
+
+        click_lantern(&pack, Click::Left);
+
+        assert!(is_collected(&pack));
+    }
+
+    #[test]
+    fn collected_with_hosting_sections() {
+        let pack = fixture_pack("lua_items");
+
+        take_changes(&pack);
+        pack.api.toggle_location_cleared("Tower").unwrap();
+
+        assert_eq!(provider_counts(&pack), [1, 0]);
+        assert_eq!(take_changes(&pack), [Change::Item(0)]);
+
+        pack.api.toggle_location_cleared("Tower").unwrap();
+
+        assert_eq!(provider_counts(&pack), [0, 0]);
+    }
+
+    #[test]
+    fn found_by_code() {
+        let pack = fixture_pack("lua_items");
+
+        assert_eq!(
+            eval::<String>(
+                &pack,
+                r#"return Tracker:FindObjectForCode("lit_lantern").Name"#
+            ),
+            "Lantern"
+        );
+        assert_eq!(
+            eval::<i32>(
+                &pack,
+                r#"
+                Tracker:FindObjectForCode("lantern"):OnLeftClickFunc()
+                return Tracker:ProviderCountForCode("lantern")
+                "#
+            ),
+            1
+        );
+    }
+
+    #[test]
+    fn state_round_trip() {
+        let pack = fixture_pack("lua_items");
+
+        click_lantern(&pack, Click::Left);
+        click_lantern(&pack, Click::Left);
+
+        let state = pack.state().unwrap();
+        let loaded_pack = fixture_pack("lua_items");
+
+        loaded_pack.load_state(&state).unwrap();
+
+        assert_eq!(provider_counts(&loaded_pack), [1, 1]);
+        assert_eq!(loaded_pack.state().unwrap(), state);
+        assert_eq!(take_changes(&loaded_pack), [Change::Item(0)]);
+    }
+
+    #[test]
+    fn changes_are_recorded() {
+        let pack = fixture_pack("lua_items");
+
+        take_changes(&pack);
+
+        assert!(click_lantern(&pack, Click::Left));
+        assert!(click_lantern(&pack, Click::Left));
+        // Already lit
+        assert!(!click_lantern(&pack, Click::Left));
+        assert!(!click_lantern(&pack, Click::Middle));
+
+        assert_eq!(take_changes(&pack), [Change::Item(0)]);
+        assert!(take_changes(&pack).is_empty());
+
+        // Scripts like autotrackers change items without clicks
+        eval::<()>(&pack, "set_lantern_stage(lantern, 0)");
+
+        assert_eq!(take_changes(&pack), [Change::Item(0)]);
+    }
+
+    #[test]
+    fn in_place_changes_are_recorded() {
+        let pack = fixture_pack("lua_items");
+
+        eval::<()>(
+            &pack,
+            r#"
+            lantern.OnLeftClickFunc = function(self)
+                self.ItemState.stage = math.min(self.ItemState.stage + 1, 2)
+            end
+            "#,
+        );
+        take_changes(&pack);
+
+        assert!(click_lantern(&pack, Click::Left));
+        assert_eq!(provider_counts(&pack), [1, 0]);
+        assert_eq!(take_changes(&pack), [Change::Item(0)]);
+
+        assert!(click_lantern(&pack, Click::Left));
+        // Already lit
+        assert!(!click_lantern(&pack, Click::Left));
+        assert_eq!(take_changes(&pack), [Change::Item(0)]);
+
+        // Without `SaveFunc`, every click counts as a change
+        eval::<()>(&pack, "lantern.SaveFunc = nil");
+
+        assert!(click_lantern(&pack, Click::Left));
+        assert_eq!(take_changes(&pack), [Change::Item(0)]);
+    }
+
+    #[test]
+    fn callbacks_can_access_the_tracker() {
+        let pack = fixture_pack("lua_items");
+
+        pack.api
+            .lua()
+            .load(
+                r#"
+                lantern.OnLeftClickFunc = function(self)
+                    set_lantern_stage(self, Tracker:ProviderCountForCode("lantern") + 1)
+                end
+
+                lantern.SaveFunc = function(self)
+                    return { stage = Tracker:ProviderCountForCode("lantern") }
+                end
+
+                lantern.LoadFunc = function(self, data)
+                    Tracker:FindObjectForCode("lantern").ItemState.stage = data.stage
+                end
+                "#,
+            )
+            .exec()
+            .unwrap();
+
+        click_lantern(&pack, Click::Left);
+
+        assert_eq!(provider_counts(&pack), [1, 0]);
+
+        let state = pack.state().unwrap();
+
+        assert_eq!(state.state.items["1"], json!({ "stage": 1 }));
+
+        let loaded_pack = fixture_pack("lua_items");
+
+        take_changes(&loaded_pack);
+        loaded_pack.load_state(&state).unwrap();
+
+        assert_eq!(provider_counts(&loaded_pack), [1, 0]);
+        assert_eq!(take_changes(&loaded_pack), [Change::Item(0)]);
+    }
+}
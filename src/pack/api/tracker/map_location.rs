@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 
+use crate::pack::rule::Rule;
 use crate::util::value_or_string;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -9,4 +10,10 @@ pub struct MapLocation {
     pub x: i32,
     #[serde(deserialize_with = "value_or_string")]
     pub y: i32,
+    /// Rules that have to be met in addition to the visibility rules of the location.
+    #[serde(default)]
+    pub restrict_visibility_rules: Vec<Rule>,
+    /// The map location is hidden if any of these rules is met.
+    #[serde(default)]
+    pub force_invisibility_rules: Vec<Rule>,
 }
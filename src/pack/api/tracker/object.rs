@@ -0,0 +1,316 @@
+//! Lua objects referring to items and sections of the [`Tracker`].
+//!
+//! The objects only store how to find what they refer to, so they stay valid while the tracker
+//! changes and every access sees the current state.
+
+use std::borrow::Cow;
+
+use mlua::{AnyUserData, Lua, UserData, UserDataFields, UserDataMethods};
+use tracing::error;
+
+use crate::pack::api::tracker::{Section, StatefulItem, Tracker};
+use crate::pack::rule::Evaluator;
+
+/// Item found with `Tracker:FindObjectForCode`.
+pub struct ItemObject {
+    pub index: usize,
+}
+
+/// Section found with `Tracker:FindObjectForCode("@Location/Section")`.
+pub struct SectionObject {
+    pub id: String,
+}
+
+fn with_tracker<R>(lua: &Lua, f: impl FnOnce(&Tracker) -> R) -> mlua::Result<R> {
+    let tracker = lua.globals().get::<AnyUserData>("Tracker")?;
+    let tracker = tracker.borrow::<Tracker>()?;
+
+    Ok(f(&tracker))
+}
+
+fn with_tracker_mut<R>(lua: &Lua, f: impl FnOnce(&mut Tracker) -> R) -> mlua::Result<R> {
+    let tracker = lua.globals().get::<AnyUserData>("Tracker")?;
+    let mut tracker = tracker.borrow_mut::<Tracker>()?;
+
+    Ok(f(&mut tracker))
+}
+
+impl ItemObject {
+    fn with_item<R>(&self, lua: &Lua, f: impl FnOnce(&StatefulItem) -> R) -> mlua::Result<R> {
+        with_tracker(lua, |tracker| {
+            let item = tracker
+                .items()
+                .get(self.index)
+                .ok_or_else(|| mlua::Error::runtime(format!("unknown item {}", self.index)))?;
+
+            Ok(f(item))
+        })?
+    }
+
+    fn update_item(
+        &self,
+        lua: &Lua,
+        f: impl FnOnce(&mut StatefulItem) -> bool,
+    ) -> mlua::Result<()> {
+        let supported = with_tracker_mut(lua, |tracker| tracker.update_item(self.index, f))?;
+
+        match supported {
+            Some(true) => {}
+            Some(false) => error!("property not supported by item {}", self.index),
+            None => error!("unknown item {}", self.index),
+        }
+
+        Ok(())
+    }
+}
+
+impl UserData for ItemObject {
+    fn add_fields<F: UserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("Name", |lua, this| {
+            this.with_item(lua, |item| item.name().into_owned())
+        });
+        fields.add_field_method_set("Name", |lua, this, name: String| {
+            this.update_item(lua, |item| {
+                item.set_name(name);
+                true
+            })
+        });
+
+        fields.add_field_method_get("Icon", |lua, this| {
+            this.with_item(lua, |item| {
+                item.icon()
+                    .map(Cow::into_owned)
+                    .or_else(|| item.display().map(|display| display.img.clone()))
+            })
+        });
+        fields.add_field_method_set("Icon", |lua, this, icon: Option<String>| {
+            this.update_item(lua, |item| {
+                item.set_icon(icon);
+                true
+            })
+        });
+
+        fields.add_field_method_get("Active", |lua, this| {
+            this.with_item(lua, StatefulItem::is_active)
+        });
+        fields.add_field_method_set("Active", |lua, this, active: bool| {
+            this.update_item(lua, |item| item.set_active(active))
+        });
+
+        fields.add_field_method_get("CurrentStage", |lua, this| {
+            this.with_item(lua, StatefulItem::current_stage)
+        });
+        fields.add_field_method_set("CurrentStage", |lua, this, stage: usize| {
+            this.update_item(lua, |item| item.set_current_stage(stage))
+        });
+
+        fields.add_field_method_get("AcquiredCount", |lua, this| {
+            this.with_item(lua, |item| item.count().unwrap_or_default())
+        });
+        fields.add_field_method_set("AcquiredCount", |lua, this, count: i32| {
+            this.update_item(lua, |item| item.set_count(count))
+        });
+
+        fields.add_field_method_get("MinCount", |lua, this| {
+            this.with_item(lua, |item| item.count_range().map_or(0, |(min, _)| min))
+        });
+        fields.add_field_method_set("MinCount", |lua, this, min: i32| {
+            this.update_item(lua, |item| {
+                let Some((_, max)) = item.count_range() else {
+                    return false;
+                };
+
+                item.set_count_range(min, max)
+            })
+        });
+
+        fields.add_field_method_get("MaxCount", |lua, this| {
+            this.with_item(lua, |item| item.count_range().map_or(0, |(_, max)| max))
+        });
+        fields.add_field_method_set("MaxCount", |lua, this, max: i32| {
+            this.update_item(lua, |item| {
+                let Some((min, _)) = item.count_range() else {
+                    return false;
+                };
+
+                item.set_count_range(min, max)
+            })
+        });
+    }
+
+    fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        methods.add_meta_method("__index", |_, _, index: mlua::Value| -> mlua::Result<()> {
+            let index = index.to_string()?;
+
+            Err(mlua::Error::runtime(format!(
+                "`Item.{index}` does not exist"
+            )))
+        });
+    }
+}
+
+impl SectionObject {
+    fn with_section<R>(&self, lua: &Lua, f: impl FnOnce(&Section) -> R) -> mlua::Result<R> {
+        with_tracker(lua, |tracker| {
+            let (_, section) = tracker
+                .section(&self.id)
+                .ok_or_else(|| mlua::Error::runtime(format!("unknown section `{}`", self.id)))?;
+
+            Ok(f(section))
+        })?
+    }
+}
+
+impl UserData for SectionObject {
+    fn add_fields<F: UserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("FullID", |_, this| Ok(this.id.clone()));
+
+        fields.add_field_method_get("Name", |lua, this| {
+            this.with_section(lua, |section| section.name.clone())
+        });
+
+        fields.add_field_method_get("ChestCount", |lua, this| {
+            this.with_section(lua, |section| section.item_count())
+        });
+
+        fields.add_field_method_get("AvailableChestCount", |lua, this| {
+            this.with_section(lua, Section::remaining)
+        });
+        fields.add_field_method_set("AvailableChestCount", |lua, this, count: u32| {
+            let found = with_tracker_mut(lua, |tracker| {
+                tracker.update_section(&this.id, |section| section.set_remaining(count))
+            })?;
+
+            if found.is_none() {
+                error!("unknown section `{}`", this.id);
+            }
+
+            Ok(())
+        });
+
+        fields.add_field_method_get("AccessibilityLevel", |lua, this| {
+            with_tracker(lua, |tracker| {
+                let (location, section) = tracker.section(&this.id).ok_or_else(|| {
+                    mlua::Error::runtime(format!("unknown section `{}`", this.id))
+                })?;
+
+                Ok(Evaluator::new(tracker, lua).section(location, section))
+            })?
+        });
+    }
+
+    fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        methods.add_meta_method("__index", |_, _, index: mlua::Value| -> mlua::Result<()> {
+            let index = index.to_string()?;
+
+            Err(mlua::Error::runtime(format!(
+                "`LocationSection.{index}` does not exist"
+            )))
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::pack::api::AccessabilityLevel;
+    use crate::pack::test_helpers::{eval, fixture_pack};
+
+    #[test]
+    fn items_are_changed_from_lua() {
+        let pack = fixture_pack("watches");
+
+        assert!(!eval::<bool>(
+            &pack,
+            r#"return Tracker:FindObjectForCode("sword").Active"#
+        ));
+
+        pack.api
+            .lua()
+            .load(r#"Tracker:FindObjectForCode("sword").Active = true"#)
+            .exec()
+            .unwrap();
+
+        assert_eq!(
+            pack.api
+                .with_tracker(|tracker| tracker.provider_count_for_item("sword"))
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            eval::<i32>(&pack, r#"return Tracker:ProviderCountForCode("sword")"#),
+            1
+        );
+
+        pack.api.dispatch_tracker_changes().unwrap();
+
+        assert_eq!(
+            eval::<Vec<String>>(&pack, "return code_calls"),
+            ["sword", "*"]
+        );
+    }
+
+    #[test]
+    fn item_properties() {
+        let pack = fixture_pack("watches");
+
+        assert_eq!(
+            eval::<String>(&pack, r#"return Tracker:FindObjectForCode("lamp").Name"#),
+            "Lamp"
+        );
+        assert_eq!(
+            eval::<String>(
+                &pack,
+                r#"
+                local lamp = Tracker:FindObjectForCode("lamp")
+                lamp.Icon = "images/lit_lamp.png"
+                return lamp.Icon
+                "#
+            ),
+            "images/lit_lamp.png"
+        );
+        assert!(
+            eval::<mlua::Value>(&pack, r#"return Tracker:FindObjectForCode("unknown")"#).is_nil()
+        );
+    }
+
+    #[test]
+    fn sections_are_changed_from_lua() {
+        let pack = fixture_pack("watches");
+
+        assert_eq!(
+            eval::<u32>(
+                &pack,
+                r#"return Tracker:FindObjectForCode("@Cave/Front").AvailableChestCount"#
+            ),
+            1
+        );
+        assert_eq!(
+            eval::<i32>(
+                &pack,
+                r#"return Tracker:FindObjectForCode("@Cave/Front").AccessibilityLevel"#
+            ),
+            AccessabilityLevel::Normal as i32
+        );
+
+        pack.api
+            .lua()
+            .load(r#"Tracker:FindObjectForCode("@Cave/Front").AvailableChestCount = 0"#)
+            .exec()
+            .unwrap();
+        pack.api.dispatch_tracker_changes().unwrap();
+
+        assert_eq!(
+            eval::<Vec<String>>(&pack, "return section_calls"),
+            ["Cave/Front"]
+        );
+        assert_eq!(
+            eval::<i32>(
+                &pack,
+                r#"return Tracker:FindObjectForCode("@Cave/Front").AccessibilityLevel"#
+            ),
+            AccessabilityLevel::Cleared as i32
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 
+use crate::pack::api::tracker::state::SectionState;
 use crate::pack::rule::Rule;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -7,5 +8,129 @@ pub struct Section {
     pub name: Option<String>,
     #[serde(default)]
     pub access_rules: Vec<Rule>,
-    // todo: more fields
+    /// The section is hidden unless these rules are met.
+    #[serde(default)]
+    pub visibility_rules: Vec<Rule>,
+    /// Number of chests in the section.
+    /// Defaults to one chest, or none if the section hosts an item.
+    #[serde(default)]
+    pub item_count: Option<u32>,
+    /// Code of an item displayed in the section, which has to be collected to clear it.
+    #[serde(default)]
+    pub hosted_item: Option<String>,
+    /// Image of the chests that are not cleared yet, relative to the pack root.
+    #[serde(default)]
+    pub chest_unopened_img: Option<String>,
+    /// Image of the cleared chests, relative to the pack root.
+    #[serde(default)]
+    pub chest_opened_img: Option<String>,
+    /// Whether the chests are displayed as a single chest with a count.
+    #[serde(default = "default_clear_as_group")]
+    pub clear_as_group: bool,
+    /// Number of cleared chests.
+    #[serde(skip)]
+    pub cleared: u32,
+}
+
+fn default_clear_as_group() -> bool {
+    true
+}
+
+impl Section {
+    pub fn item_count(&self) -> u32 {
+        self.item_count
+            .unwrap_or(if self.hosted_item.is_some() { 0 } else { 1 })
+    }
+
+    /// Number of chests that are not cleared yet.
+    pub fn remaining(&self) -> u32 {
+        self.item_count().saturating_sub(self.cleared)
+    }
+
+    /// Whether all chests are cleared.
+    /// The section also needs its hosted item, see [`Tracker::is_section_cleared`].
+    ///
+    /// [`Tracker::is_section_cleared`]: crate::pack::api::Tracker::is_section_cleared
+    pub fn chests_cleared(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    /// Clears all chests or restores all of them.
+    pub fn set_cleared(&mut self, cleared: bool) {
+        self.cleared = if cleared { self.item_count() } else { 0 };
+    }
+
+    /// Sets the number of chests that are not cleared yet, clamped to the chest count.
+    pub fn set_remaining(&mut self, remaining: u32) {
+        self.cleared = self.item_count() - remaining.min(self.item_count());
+    }
+
+    /// Returns `false` if all chests are cleared already.
+    pub fn clear_chest(&mut self) -> bool {
+        if self.chests_cleared() {
+            return false;
+        }
+
+        self.cleared += 1;
+
+        true
+    }
+
+    /// Returns `false` if no chest is cleared.
+    pub fn restore_chest(&mut self) -> bool {
+        if self.cleared == 0 {
+            return false;
+        }
+
+        self.cleared -= 1;
+
+        true
+    }
+
+    pub fn state(&self) -> SectionState {
+        SectionState {
+            cleared: self.cleared,
+        }
+    }
+
+    pub fn load_state(&mut self, state: &SectionState) {
+        self.cleared = state.cleared.min(self.item_count());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{Section, SectionState};
+    use crate::util::deserialize_hjson;
+
+    #[test]
+    fn chests_are_cleared_one_by_one() {
+        let mut section =
+            deserialize_hjson::<Section>(r#"{ name: "Chests", item_count: 2 }"#).unwrap();
+
+        assert!(section.clear_as_group);
+        assert_eq!(section.remaining(), 2);
+
+        assert!(section.clear_chest());
+        assert!(section.clear_chest());
+        assert!(!section.clear_chest());
+        assert!(section.chests_cleared());
+
+        assert!(section.restore_chest());
+        assert_eq!(section.remaining(), 1);
+
+        section.set_cleared(false);
+        assert!(!section.restore_chest());
+    }
+
+    #[test]
+    fn loaded_state_is_clamped() {
+        let mut section = deserialize_hjson::<Section>(r#"{ name: "Chest" }"#).unwrap();
+
+        section.load_state(&SectionState { cleared: 3 });
+
+        assert_eq!(section.state(), SectionState { cleared: 1 });
+    }
 }
@@ -0,0 +1,44 @@
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+/// Snapshot of everything the user can change in a [`Tracker`](super::Tracker),
+/// laid out like the `tracker_state` of PopTracker's state files.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct TrackerState {
+    /// Item states keyed by the ids of the items, see [`item_id`](super::item_id).
+    /// Lua items store the value returned by their `SaveFunc`, the others an [`ItemState`].
+    #[serde(default)]
+    pub items: IndexMap<String, serde_json::Value>,
+    /// Section states keyed by `location_id/section_name`.
+    #[serde(default, rename = "locations")]
+    pub sections: IndexMap<String, SectionState>,
+    /// Ids of pinned locations, most recently pinned first.
+    /// PopTracker has no pins and ignores them.
+    #[serde(default)]
+    pub pins: Vec<String>,
+    /// Notes keyed by location id. PopTracker has no notes and ignores them.
+    #[serde(default)]
+    pub notes: IndexMap<String, String>,
+}
+
+/// State of an item that isn't a Lua item, using the fields of PopTracker's item states.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ItemState {
+    /// Whether the item is active.
+    /// Composite toggles store their left state in bit 0 and their right state in bit 1.
+    #[serde(default)]
+    pub stage1: i32,
+    /// Active stage index of progressive items.
+    #[serde(default)]
+    pub stage2: i32,
+    /// Quantity of consumables.
+    #[serde(default)]
+    pub count: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct SectionState {
+    /// Number of cleared chests.
+    #[serde(default)]
+    pub cleared: u32,
+}
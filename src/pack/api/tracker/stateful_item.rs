@@ -1,14 +1,28 @@
+use std::borrow::Cow;
+
+use mlua::AnyUserData;
 use tracing::{error, instrument};
 
 use crate::pack::api::tracker::item::{
-    self, CompositeToggle, Consumable, Item, Progressive, ProgressiveToggle, Static, Toggle,
-    ToggleBadged,
+    self, CompositeToggle, Consumable, Display, Item, Progressive, ProgressiveToggle, Stage,
+    Static, Toggle, ToggleBadged,
 };
+use crate::pack::api::tracker::lua_item::LuaItemHandle;
+use crate::pack::api::tracker::state::ItemState;
 
 #[derive(Debug)]
 pub struct StatefulItem {
     common: item::Common,
     variant: StatefulItemVariant,
+    /// Image set by the pack, replacing the image of the current state.
+    icon: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Click {
+    Left,
+    Right,
+    Middle,
 }
 
 impl StatefulItem {
@@ -18,42 +32,531 @@ impl StatefulItem {
         Self {
             common,
             variant: StatefulItemVariant::new(variant),
+            icon: None,
         }
     }
 
-    #[instrument(level = "error")]
-    pub fn provider_count(&self, item_code: &str) -> i32 {
-        let common_codes_match = self.common.codes.contains(item_code);
+    /// Item implemented by the pack with a [`LuaItem`](super::LuaItem) userdata.
+    pub fn lua(item: AnyUserData) -> Self {
+        Self {
+            common: item::Common::default(),
+            variant: StatefulItemVariant::Lua {
+                item: LuaItemHandle::new(item),
+            },
+            icon: None,
+        }
+    }
 
+    /// The handle of items implemented by the pack.
+    pub fn lua_item(&self) -> Option<&LuaItemHandle> {
         match &self.variant {
-            StatefulItemVariant::Static { item: _ } => common_codes_match as i32,
+            StatefulItemVariant::Lua { item } => Some(item),
+            _ => None,
+        }
+    }
+
+    pub fn lua_item_mut(&mut self) -> Option<&mut LuaItemHandle> {
+        match &mut self.variant {
+            StatefulItemVariant::Lua { item } => Some(item),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> Cow<'_, str> {
+        match &self.variant {
+            StatefulItemVariant::Lua { item } => Cow::Owned(item.name()),
+            _ => Cow::Borrowed(&self.common.name),
+        }
+    }
+
+    pub fn set_name(&mut self, name: String) {
+        match &self.variant {
+            StatefulItemVariant::Lua { item } => item.set_name(name),
+            _ => self.common.name = name,
+        }
+    }
+
+    /// Image to display instead of the image of the current state.
+    pub fn icon(&self) -> Option<Cow<'_, str>> {
+        match &self.variant {
+            StatefulItemVariant::Lua { item } => item.icon().map(Cow::Owned),
+            _ => self.icon.as_deref().map(Cow::Borrowed),
+        }
+    }
+
+    pub fn set_icon(&mut self, icon: Option<String>) {
+        match &self.variant {
+            StatefulItemVariant::Lua { item } => item.set_icon(icon),
+            _ => self.icon = icon,
+        }
+    }
+
+    /// Whether the item provides the code in any of its states.
+    pub fn has_code(&self, code: &str) -> bool {
+        if self.common.codes.contains(code) {
+            return true;
+        }
+
+        match &self.variant {
+            StatefulItemVariant::Progressive { item, .. } => {
+                item.stages.iter().any(|stage| stage.codes.contains(code))
+            }
+            StatefulItemVariant::ProgressiveToggle { item, .. } => {
+                item.stages.iter().any(|stage| stage.codes.contains(code))
+            }
+            StatefulItemVariant::CompositeToggle { item, .. } => {
+                item.item_left == code
+                    || item.item_right == code
+                    || item.images.iter().any(|image| image.codes.contains(code))
+            }
+            StatefulItemVariant::Lua { item } => item.can_provide_code(code),
+            _ => false,
+        }
+    }
+
+    /// How to display the item in its current state.
+    pub fn display(&self) -> Option<&Display> {
+        match &self.variant {
+            StatefulItemVariant::Static { item } => Some(&item.display),
+            StatefulItemVariant::Progressive {
+                item,
+                active_stage_index,
+                disabled: _,
+            } => item
+                .stages
+                .get(*active_stage_index)
+                .map(|stage| &stage.display),
+            StatefulItemVariant::Toggle { item, disabled: _ } => Some(&item.display),
+            StatefulItemVariant::Consumable { item, count: _ } => Some(&item.display),
+            StatefulItemVariant::ProgressiveToggle {
+                item,
+                active: _,
+                active_stage_index,
+            } => item
+                .stages
+                .get(*active_stage_index)
+                .map(|stage| &stage.display),
+            StatefulItemVariant::CompositeToggle { item, left, right } => item
+                .images
+                .iter()
+                .find(|image| image.left == *left && image.right == *right)
+                .map(|image| &image.display),
+            StatefulItemVariant::ToggleBadged { item, disabled: _ } => Some(&item.display),
+            // Lua items only have an icon
+            StatefulItemVariant::Lua { item: _ } => None,
+        }
+    }
+
+    /// Whether the item is displayed as active, inactive items are greyed out.
+    /// Hosted items use [`is_collected`](Self::is_collected) instead.
+    pub fn is_active(&self) -> bool {
+        match &self.variant {
+            StatefulItemVariant::Static { item: _ } => true,
+            StatefulItemVariant::Progressive {
+                item: _,
+                active_stage_index: _,
+                disabled,
+            } => !disabled,
+            StatefulItemVariant::Toggle { item: _, disabled } => !disabled,
+            StatefulItemVariant::Consumable { item: _, count } => *count > 0,
+            StatefulItemVariant::ProgressiveToggle {
+                item: _,
+                active,
+                active_stage_index: _,
+            } => *active,
+            // The images of composite toggles already reflect their state
+            StatefulItemVariant::CompositeToggle { .. } => true,
+            StatefulItemVariant::ToggleBadged { item: _, disabled } => !disabled,
+            StatefulItemVariant::Lua { item: _ } => true,
+        }
+    }
+
+    /// Whether the item counts as collected for the sections hosting it with the code.
+    /// Static items are never collected. Progressive items that can't be disabled are
+    /// collected past their first stage, Lua items while they provide the code.
+    pub fn is_collected(&self, code: &str) -> bool {
+        match &self.variant {
+            StatefulItemVariant::Static { item: _ } => false,
+            StatefulItemVariant::Progressive {
+                item,
+                active_stage_index,
+                disabled,
+            } => !disabled && (item.allow_disabled || *active_stage_index > 0),
+            StatefulItemVariant::Toggle { item: _, disabled }
+            | StatefulItemVariant::ToggleBadged { item: _, disabled } => !disabled,
+            StatefulItemVariant::Consumable { item: _, count } => *count > 0,
+            StatefulItemVariant::ProgressiveToggle {
+                item: _,
+                active,
+                active_stage_index: _,
+            } => *active,
+            StatefulItemVariant::CompositeToggle {
+                item: _,
+                left,
+                right,
+            } => *left && *right,
+            StatefulItemVariant::Lua { item } => item.provides_code(code) > 0,
+        }
+    }
+
+    /// Quantity to display as an overlay.
+    pub fn count(&self) -> Option<i32> {
+        match &self.variant {
+            StatefulItemVariant::Consumable { item: _, count } => Some(*count),
+            _ => None,
+        }
+    }
+
+    /// Index of the active stage of progressive items.
+    /// For composite toggles the left and right state as bits.
+    pub fn current_stage(&self) -> usize {
+        match &self.variant {
+            StatefulItemVariant::Progressive {
+                active_stage_index, ..
+            }
+            | StatefulItemVariant::ProgressiveToggle {
+                active_stage_index, ..
+            } => *active_stage_index,
+            StatefulItemVariant::CompositeToggle {
+                item: _,
+                left,
+                right,
+            } => (*left as usize) | ((*right as usize) << 1),
+            _ => 0,
+        }
+    }
+
+    /// Minimum and maximum quantity of consumables. Negative maximums mean unlimited.
+    pub fn count_range(&self) -> Option<(i32, i32)> {
+        match &self.variant {
+            StatefulItemVariant::Consumable { item, count: _ } => {
+                Some((item.min_quantity, item.max_quantity))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns `false` if the item has no active state.
+    pub fn set_active(&mut self, active: bool) -> bool {
+        match &mut self.variant {
+            StatefulItemVariant::Progressive { item, disabled, .. } => {
+                *disabled = !active && item.allow_disabled;
+            }
+            StatefulItemVariant::Toggle { item: _, disabled }
+            | StatefulItemVariant::ToggleBadged { item: _, disabled } => *disabled = !active,
+            StatefulItemVariant::ProgressiveToggle {
+                active: current, ..
+            } => *current = active,
+            _ => return false,
+        }
+
+        true
+    }
+
+    /// Collects or uncollects the item, e.g. for the sections hosting it,
+    /// see [`is_collected`](Self::is_collected).
+    /// Returns `false` for static and Lua items, which can't be collected this way.
+    pub fn set_collected(&mut self, collected: bool) -> bool {
+        match &mut self.variant {
+            StatefulItemVariant::Static { item: _ } | StatefulItemVariant::Lua { item: _ } => {
+                return false;
+            }
             StatefulItemVariant::Progressive {
                 item,
                 active_stage_index,
                 disabled,
             } => {
-                if item.allow_disabled && *disabled {
-                    return 0;
+                if item.allow_disabled {
+                    *disabled = !collected;
+                } else if !collected {
+                    *active_stage_index = 0;
+                } else if *active_stage_index == 0 {
+                    *active_stage_index = item.stages.len().saturating_sub(1).min(1);
+                }
+            }
+            StatefulItemVariant::Toggle { item: _, disabled }
+            | StatefulItemVariant::ToggleBadged { item: _, disabled } => *disabled = !collected,
+            StatefulItemVariant::Consumable { item, count } => {
+                if !collected {
+                    *count = clamp_count(0, item.min_quantity, item.max_quantity);
+                } else if *count <= 0 {
+                    *count = clamp_count(1, item.min_quantity, item.max_quantity);
                 }
+            }
+            StatefulItemVariant::ProgressiveToggle {
+                item: _,
+                active,
+                active_stage_index: _,
+            } => *active = collected,
+            StatefulItemVariant::CompositeToggle {
+                item: _,
+                left,
+                right,
+            } => {
+                *left = collected;
+                *right = collected;
+            }
+        }
 
-                let Some(stages_to_check) = item.stages.get(*active_stage_index..) else {
-                    error!("active stage index out of bounds");
-                    return 0;
+        true
+    }
+
+    /// Returns `false` if the item has no stages.
+    /// Stages past the last one select the last stage.
+    pub fn set_current_stage(&mut self, stage: usize) -> bool {
+        match &mut self.variant {
+            StatefulItemVariant::Progressive {
+                item,
+                active_stage_index,
+                ..
+            } => *active_stage_index = stage.min(item.stages.len().saturating_sub(1)),
+            StatefulItemVariant::ProgressiveToggle {
+                item,
+                active_stage_index,
+                ..
+            } => *active_stage_index = stage.min(item.stages.len().saturating_sub(1)),
+            StatefulItemVariant::CompositeToggle {
+                item: _,
+                left,
+                right,
+            } => {
+                *left = stage & 0b01 != 0;
+                *right = stage & 0b10 != 0;
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    /// Sets the quantity of a consumable, clamped to its range.
+    /// Returns `false` if the item isn't a consumable.
+    pub fn set_count(&mut self, new_count: i32) -> bool {
+        let StatefulItemVariant::Consumable { item, count } = &mut self.variant else {
+            return false;
+        };
+
+        *count = clamp_count(new_count, item.min_quantity, item.max_quantity);
+
+        true
+    }
+
+    /// Changes the range of a consumable and clamps its quantity to it.
+    /// Returns `false` if the item isn't a consumable.
+    pub fn set_count_range(&mut self, min_count: i32, max_count: i32) -> bool {
+        let StatefulItemVariant::Consumable { item, count } = &mut self.variant else {
+            return false;
+        };
+
+        item.min_quantity = min_count;
+        item.max_quantity = max_count;
+        *count = clamp_count(*count, min_count, max_count);
+
+        true
+    }
+
+    pub fn state(&self) -> ItemState {
+        match &self.variant {
+            StatefulItemVariant::Static { item: _ } => ItemState::default(),
+            StatefulItemVariant::Progressive {
+                item: _,
+                active_stage_index,
+                disabled,
+            } => ItemState {
+                stage1: !disabled as i32,
+                stage2: *active_stage_index as i32,
+                ..ItemState::default()
+            },
+            StatefulItemVariant::Toggle { item: _, disabled } => ItemState {
+                stage1: !disabled as i32,
+                ..ItemState::default()
+            },
+            StatefulItemVariant::Consumable { item: _, count } => ItemState {
+                count: *count,
+                ..ItemState::default()
+            },
+            StatefulItemVariant::ProgressiveToggle {
+                item: _,
+                active,
+                active_stage_index,
+            } => ItemState {
+                stage1: *active as i32,
+                stage2: *active_stage_index as i32,
+                ..ItemState::default()
+            },
+            StatefulItemVariant::CompositeToggle {
+                item: _,
+                left,
+                right,
+            } => ItemState {
+                stage1: (*left as i32) | ((*right as i32) << 1),
+                ..ItemState::default()
+            },
+            StatefulItemVariant::ToggleBadged { item: _, disabled } => ItemState {
+                stage1: !disabled as i32,
+                ..ItemState::default()
+            },
+            // `SaveFunc` may access the tracker, so it is called by `Api::tracker_state`
+            StatefulItemVariant::Lua { item: _ } => ItemState::default(),
+        }
+    }
+
+    pub fn load_state(&mut self, state: &ItemState) {
+        let ItemState {
+            stage1,
+            stage2,
+            count,
+        } = *state;
+        let stage_index = usize::try_from(stage2).unwrap_or_default();
+
+        match &mut self.variant {
+            StatefulItemVariant::Static { item: _ } => {}
+            StatefulItemVariant::Progressive {
+                item,
+                active_stage_index,
+                disabled,
+            } => {
+                *disabled = stage1 == 0 && item.allow_disabled;
+                *active_stage_index = stage_index.min(item.stages.len().saturating_sub(1));
+            }
+            StatefulItemVariant::Toggle { item: _, disabled } => *disabled = stage1 == 0,
+            StatefulItemVariant::Consumable {
+                item,
+                count: current_count,
+            } => *current_count = clamp_count(count, item.min_quantity, item.max_quantity),
+            StatefulItemVariant::ProgressiveToggle {
+                item,
+                active,
+                active_stage_index,
+            } => {
+                *active = stage1 != 0;
+                *active_stage_index = stage_index.min(item.stages.len().saturating_sub(1));
+            }
+            StatefulItemVariant::CompositeToggle {
+                item: _,
+                left,
+                right,
+            } => {
+                *left = stage1 & 0b01 != 0;
+                *right = stage1 & 0b10 != 0;
+            }
+            StatefulItemVariant::ToggleBadged { item: _, disabled } => *disabled = stage1 == 0,
+            // `LoadFunc` may access the tracker, so it is called by `Api::load_tracker_state`
+            StatefulItemVariant::Lua { item: _ } => {}
+        }
+    }
+
+    /// Changes the item state according to the click.
+    /// Returns `true` if the state changed.
+    pub fn click(&mut self, click: Click) -> bool {
+        match click {
+            Click::Left => self.left_click(),
+            Click::Right => self.right_click(),
+            Click::Middle => self.middle_click(),
+        }
+    }
+
+    pub fn left_click(&mut self) -> bool {
+        match &mut self.variant {
+            StatefulItemVariant::Static { item: _ } => false,
+            StatefulItemVariant::Progressive {
+                item,
+                active_stage_index,
+                disabled,
+            } => step_progressive(item, active_stage_index, disabled, Step::Forward),
+            StatefulItemVariant::Toggle { item: _, disabled } => toggle(disabled),
+            StatefulItemVariant::Consumable { item, count } => {
+                let max_quantity = if item.max_quantity < 0 {
+                    i32::MAX
+                } else {
+                    item.max_quantity
                 };
+                let new_count = count.saturating_add(item.increment).min(max_quantity);
 
-                for stage in stages_to_check {
-                    let stage_matches = stage.codes.contains(item_code);
+                set(count, new_count)
+            }
+            StatefulItemVariant::ProgressiveToggle {
+                item: _,
+                active,
+                active_stage_index: _,
+            } => toggle(active),
+            StatefulItemVariant::CompositeToggle {
+                item: _,
+                left,
+                right: _,
+            } => toggle(left),
+            StatefulItemVariant::ToggleBadged { item: _, disabled } => toggle(disabled),
+            // The callbacks may access the tracker, so they are called by `Api::click_item`
+            StatefulItemVariant::Lua { item: _ } => false,
+        }
+    }
 
-                    if stage_matches {
-                        return 1;
-                    }
+    pub fn right_click(&mut self) -> bool {
+        match &mut self.variant {
+            StatefulItemVariant::Static { item: _ } => false,
+            StatefulItemVariant::Progressive {
+                item,
+                active_stage_index,
+                disabled,
+            } => step_progressive(item, active_stage_index, disabled, Step::Backward),
+            StatefulItemVariant::Toggle { item: _, disabled } => toggle(disabled),
+            StatefulItemVariant::Consumable { item, count } => {
+                let new_count = count.saturating_sub(item.decrement).max(item.min_quantity);
 
-                    if !stage.inherit_codes {
-                        break;
-                    }
+                set(count, new_count)
+            }
+            // Cycles through the stages without changing the active state
+            StatefulItemVariant::ProgressiveToggle {
+                item,
+                active: _,
+                active_stage_index,
+            } => {
+                let new_stage_index = match *active_stage_index + 1 {
+                    next_stage_index if next_stage_index < item.stages.len() => next_stage_index,
+                    _ if item.r#loop => 0,
+                    _ => *active_stage_index,
+                };
+
+                set(active_stage_index, new_stage_index)
+            }
+            StatefulItemVariant::CompositeToggle {
+                item: _,
+                left: _,
+                right,
+            } => toggle(right),
+            StatefulItemVariant::ToggleBadged { item: _, disabled } => toggle(disabled),
+            StatefulItemVariant::Lua { item: _ } => false,
+        }
+    }
+
+    pub fn middle_click(&mut self) -> bool {
+        match &mut self.variant {
+            // Toggles the item without losing the current stage
+            StatefulItemVariant::Progressive {
+                item,
+                active_stage_index: _,
+                disabled,
+            } if item.allow_disabled => toggle(disabled),
+            _ => false,
+        }
+    }
+
+    #[instrument(level = "error")]
+    pub fn provider_count(&self, item_code: &str) -> i32 {
+        let common_codes_match = self.common.codes.contains(item_code);
+
+        match &self.variant {
+            StatefulItemVariant::Static { item: _ } => common_codes_match as i32,
+            StatefulItemVariant::Progressive {
+                item,
+                active_stage_index,
+                disabled,
+            } => {
+                if item.allow_disabled && *disabled {
+                    return 0;
                 }
 
-                0
+                stages_provider_count(&item.stages, *active_stage_index, item_code)
             }
             StatefulItemVariant::Toggle { item: _, disabled } => {
                 if !common_codes_match {
@@ -73,7 +576,17 @@ impl StatefulItem {
 
                 *count
             }
-            StatefulItemVariant::ProgressiveToggle { item: _ } => todo!(),
+            StatefulItemVariant::ProgressiveToggle {
+                item,
+                active,
+                active_stage_index,
+            } => {
+                if !*active {
+                    return 0;
+                }
+
+                stages_provider_count(&item.stages, *active_stage_index, item_code)
+            }
             StatefulItemVariant::CompositeToggle { item, left, right } => {
                 let mut left_count = 0;
                 let mut right_count = 0;
@@ -109,6 +622,7 @@ impl StatefulItem {
                     1
                 }
             }
+            StatefulItemVariant::Lua { item } => item.provides_code(item_code),
         }
     }
 }
@@ -133,6 +647,8 @@ pub enum StatefulItemVariant {
     },
     ProgressiveToggle {
         item: ProgressiveToggle,
+        active: bool,
+        active_stage_index: usize,
     },
     CompositeToggle {
         item: CompositeToggle,
@@ -143,6 +659,9 @@ pub enum StatefulItemVariant {
         item: ToggleBadged,
         disabled: bool,
     },
+    Lua {
+        item: LuaItemHandle,
+    },
 }
 
 impl StatefulItemVariant {
@@ -151,27 +670,515 @@ impl StatefulItemVariant {
             item::Variant::Static(item) => Self::Static { item },
             item::Variant::Progressive(item) => Self::Progressive {
                 active_stage_index: item.initial_stage_idx,
-                disabled: false,
+                disabled: item.allow_disabled && item.initial_stage_idx == 0,
                 item,
             },
             item::Variant::Toggle(item) => Self::Toggle {
-                disabled: item.initial_active_state,
+                disabled: !item.initial_active_state,
                 item,
             },
             item::Variant::Consumable(item) => Self::Consumable {
                 count: item.initial_quantity,
                 item,
             },
-            item::Variant::ProgressiveToggle(item) => Self::ProgressiveToggle { item },
+            item::Variant::ProgressiveToggle(item) => Self::ProgressiveToggle {
+                active: item.initial_active_state,
+                active_stage_index: item.initial_stage_idx,
+                item,
+            },
             item::Variant::CompositeToggle(item) => Self::CompositeToggle {
                 item,
                 left: false,
                 right: false,
             },
             item::Variant::ToggleBadged(item) => Self::ToggleBadged {
-                disabled: item.initial_active_state,
+                disabled: !item.initial_active_state,
                 item,
             },
         }
     }
 }
+
+/// Provider count of the active stage.
+/// Stages inherit the codes of previous stages unless disabled.
+fn stages_provider_count(stages: &[Stage], active_stage_index: usize, item_code: &str) -> i32 {
+    let Some(stages_to_check) = stages.get(..=active_stage_index) else {
+        error!("active stage index out of bounds");
+        return 0;
+    };
+
+    for stage in stages_to_check.iter().rev() {
+        let stage_matches = stage.codes.contains(item_code);
+
+        if stage_matches {
+            return 1;
+        }
+
+        if !stage.inherit_codes {
+            break;
+        }
+    }
+
+    0
+}
+
+enum Step {
+    Forward,
+    Backward,
+}
+
+/// Steps through the stages of a progressive item.
+/// If disabling is allowed, the disabled state comes before the first stage.
+fn step_progressive(
+    item: &Progressive,
+    active_stage_index: &mut usize,
+    disabled: &mut bool,
+    step: Step,
+) -> bool {
+    let offset = item.allow_disabled as usize;
+    let position_count = item.stages.len() + offset;
+    let position = if item.allow_disabled && *disabled {
+        0
+    } else {
+        *active_stage_index + offset
+    };
+
+    if position_count == 0 {
+        return false;
+    }
+
+    let new_position = match step {
+        Step::Forward if position + 1 < position_count => position + 1,
+        Step::Forward if item.r#loop => 0,
+        Step::Backward if position > 0 => position - 1,
+        Step::Backward if item.r#loop => position_count - 1,
+        _ => position,
+    };
+
+    if new_position == position {
+        return false;
+    }
+
+    if new_position < offset {
+        *disabled = true;
+        *active_stage_index = 0;
+    } else {
+        *disabled = false;
+        *active_stage_index = new_position - offset;
+    }
+
+    true
+}
+
+fn clamp_count(count: i32, min_count: i32, max_count: i32) -> i32 {
+    let max_count = if max_count < 0 { i32::MAX } else { max_count };
+
+    count.min(max_count).max(min_count)
+}
+
+fn toggle(value: &mut bool) -> bool {
+    *value = !*value;
+
+    true
+}
+
+fn set<T: PartialEq>(value: &mut T, new_value: T) -> bool {
+    if *value == new_value {
+        return false;
+    }
+
+    *value = new_value;
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{Click, ItemState, StatefulItem};
+    use crate::pack::api::tracker::Item;
+    use crate::util::deserialize_hjson;
+
+    fn item(hjson: &str) -> StatefulItem {
+        StatefulItem::new(deserialize_hjson::<Item>(hjson).unwrap())
+    }
+
+    #[track_caller]
+    fn assert_provides(item: &StatefulItem, codes: &[(&str, i32)]) {
+        for &(code, count) in codes {
+            assert_eq!(
+                item.provider_count(code),
+                count,
+                "provider count of `{code}`"
+            );
+        }
+    }
+
+    #[test]
+    fn toggle() {
+        let mut toggle = item(r#"{ type: "toggle", codes: "hookshot", img: "hookshot.png" }"#);
+
+        assert_provides(&toggle, &[("hookshot", 0)]);
+        assert!(toggle.click(Click::Left));
+        assert_provides(&toggle, &[("hookshot", 1)]);
+        assert!(toggle.click(Click::Right));
+        assert_provides(&toggle, &[("hookshot", 0)]);
+        assert!(!toggle.click(Click::Middle));
+    }
+
+    #[test]
+    fn toggle_initially_active() {
+        let toggle = item(
+            r#"{ type: "toggle", codes: "boots", img: "boots.png", initial_active_state: true }"#,
+        );
+
+        assert_provides(&toggle, &[("boots", 1)]);
+    }
+
+    #[test]
+    fn progressive() {
+        let mut sword = item(
+            r#"{
+                type: "progressive",
+                stages: [
+                    { img: "1.png", codes: "sword1" },
+                    { img: "2.png", codes: "sword2" },
+                    { img: "3.png", codes: "sword3", inherit_codes: false },
+                ],
+            }"#,
+        );
+
+        assert!(!sword.is_active());
+        assert_provides(&sword, &[("sword1", 0), ("sword2", 0), ("sword3", 0)]);
+
+        assert!(sword.click(Click::Left));
+        assert_provides(&sword, &[("sword1", 1), ("sword2", 0), ("sword3", 0)]);
+
+        assert!(sword.click(Click::Left));
+        assert_provides(&sword, &[("sword1", 1), ("sword2", 1), ("sword3", 0)]);
+
+        assert!(sword.click(Click::Left));
+        assert_provides(&sword, &[("sword1", 0), ("sword2", 0), ("sword3", 1)]);
+
+        // Loops back to the disabled state
+        assert!(sword.click(Click::Left));
+        assert!(!sword.is_active());
+
+        assert!(sword.click(Click::Right));
+        assert_provides(&sword, &[("sword1", 0), ("sword2", 0), ("sword3", 1)]);
+    }
+
+    #[test]
+    fn progressive_without_loop_or_disabled() {
+        let mut bow = item(
+            r#"{
+                type: "progressive",
+                allow_disabled: false,
+                loop: false,
+                stages: [
+                    { img: "1.png", codes: "bow" },
+                    { img: "2.png", codes: "silver_bow" },
+                ],
+            }"#,
+        );
+
+        assert_provides(&bow, &[("bow", 1), ("silver_bow", 0)]);
+        assert!(!bow.click(Click::Right));
+        assert!(bow.click(Click::Left));
+        assert_provides(&bow, &[("bow", 1), ("silver_bow", 1)]);
+        assert!(!bow.click(Click::Left));
+        assert!(!bow.click(Click::Middle));
+    }
+
+    #[test]
+    fn progressive_middle_click_keeps_stage() {
+        let mut glove = item(
+            r#"{
+                type: "progressive",
+                initial_stage_idx: 1,
+                stages: [
+                    { img: "1.png", codes: "glove" },
+                    { img: "2.png", codes: "mitts" },
+                ],
+            }"#,
+        );
+
+        assert_provides(&glove, &[("mitts", 1)]);
+        assert!(glove.click(Click::Middle));
+        assert_provides(&glove, &[("mitts", 0)]);
+        assert!(glove.click(Click::Middle));
+        assert_provides(&glove, &[("mitts", 1)]);
+    }
+
+    #[test]
+    fn progressive_toggle() {
+        let mut bow = item(
+            r#"{
+                type: "progressive_toggle",
+                stages: [
+                    { img: "bow.png", codes: "bow" },
+                    { img: "silver.png", codes: "silver_arrows" },
+                    { img: "fire.png", codes: "fire_arrows", inherit_codes: false },
+                ],
+            }"#,
+        );
+
+        assert!(!bow.is_active());
+        assert_provides(&bow, &[("bow", 0), ("silver_arrows", 0)]);
+
+        assert!(bow.click(Click::Left));
+        assert_provides(&bow, &[("bow", 1), ("silver_arrows", 0)]);
+
+        // Cycling stages keeps the item active
+        assert!(bow.click(Click::Right));
+        assert!(bow.is_active());
+        assert_provides(&bow, &[("bow", 1), ("silver_arrows", 1)]);
+        assert_eq!(bow.display().unwrap().img, "silver.png");
+
+        assert!(bow.click(Click::Right));
+        assert_provides(
+            &bow,
+            &[("bow", 0), ("silver_arrows", 0), ("fire_arrows", 1)],
+        );
+
+        assert!(bow.click(Click::Left));
+        assert_provides(&bow, &[("fire_arrows", 0)]);
+
+        // Loops back to the first stage
+        assert!(bow.click(Click::Right));
+        assert_eq!(bow.display().unwrap().img, "bow.png");
+        assert!(!bow.click(Click::Middle));
+    }
+
+    #[test]
+    fn progressive_toggle_initial_state() {
+        let mut lantern = item(
+            r#"{
+                type: "progressive_toggle",
+                initial_active_state: true,
+                initial_stage_idx: 1,
+                loop: false,
+                stages: [
+                    { img: "off.png", codes: "lantern" },
+                    { img: "on.png", codes: "lit_lantern" },
+                ],
+            }"#,
+        );
+
+        assert_provides(&lantern, &[("lantern", 1), ("lit_lantern", 1)]);
+        assert!(!lantern.click(Click::Right));
+    }
+
+    #[test]
+    fn consumable() {
+        let mut bombs = item(
+            r#"{
+                type: "consumable",
+                codes: "bombs",
+                img: "bombs.png",
+                max_quantity: 10,
+                increment: 4,
+                decrement: 3,
+            }"#,
+        );
+
+        assert_provides(&bombs, &[("bombs", 0)]);
+        assert!(!bombs.click(Click::Right));
+        assert!(bombs.click(Click::Left));
+        assert!(bombs.click(Click::Left));
+        assert!(bombs.click(Click::Left));
+        assert_provides(&bombs, &[("bombs", 10)]);
+        assert!(!bombs.click(Click::Left));
+        assert!(bombs.click(Click::Right));
+        assert_eq!(bombs.count(), Some(7));
+    }
+
+    #[test]
+    fn composite_toggle() {
+        let mut medallions = item(
+            r#"{
+                type: "composite_toggle",
+                item_left: "bombos",
+                item_right: "ether",
+                images: [
+                    { left: false, right: false, img: "none.png" },
+                    { left: true, right: false, img: "left.png" },
+                    { left: false, right: true, img: "right.png" },
+                    { left: true, right: true, img: "both.png" },
+                ],
+            }"#,
+        );
+
+        assert!(medallions.click(Click::Right));
+        assert_provides(&medallions, &[("bombos", 0), ("ether", 1)]);
+        assert_eq!(medallions.display().unwrap().img, "right.png");
+
+        assert!(medallions.click(Click::Left));
+        assert_provides(&medallions, &[("bombos", 1), ("ether", 1)]);
+        assert_eq!(medallions.display().unwrap().img, "both.png");
+    }
+
+    #[test]
+    fn state_round_trip() {
+        let mut sword = item(
+            r#"{
+                type: "progressive",
+                stages: [
+                    { img: "1.png", codes: "sword1" },
+                    { img: "2.png", codes: "sword2" },
+                ],
+            }"#,
+        );
+
+        sword.click(Click::Left);
+        sword.click(Click::Left);
+
+        let state = sword.state();
+        let mut loaded_sword = item(
+            r#"{
+                type: "progressive",
+                stages: [
+                    { img: "1.png", codes: "sword1" },
+                    { img: "2.png", codes: "sword2" },
+                ],
+            }"#,
+        );
+
+        loaded_sword.load_state(&state);
+
+        assert_eq!(loaded_sword.state(), state);
+        assert_provides(&loaded_sword, &[("sword2", 1)]);
+    }
+
+    #[test]
+    fn loaded_count_is_clamped() {
+        let mut bombs =
+            item(r#"{ type: "consumable", codes: "bombs", img: "bombs.png", max_quantity: 10 }"#);
+
+        bombs.load_state(&ItemState {
+            count: 99,
+            ..ItemState::default()
+        });
+
+        assert_eq!(bombs.count(), Some(10));
+
+        bombs.load_state(&ItemState {
+            count: -5,
+            ..ItemState::default()
+        });
+
+        assert_eq!(bombs.count(), Some(0));
+    }
+
+    #[test]
+    fn static_item() {
+        let mut triforce = item(r#"{ type: "static", codes: "triforce", img: "triforce.png" }"#);
+
+        assert_provides(&triforce, &[("triforce", 1)]);
+        assert!(!triforce.click(Click::Left));
+    }
+
+    #[test]
+    fn static_item_is_never_collected() {
+        let triforce = item(r#"{ type: "static", codes: "triforce", img: "triforce.png" }"#);
+
+        assert!(!triforce.is_collected("triforce"));
+    }
+
+    #[test]
+    fn toggle_is_collected_while_active() {
+        let mut hookshot = item(r#"{ type: "toggle", codes: "hookshot", img: "hookshot.png" }"#);
+
+        assert!(!hookshot.is_collected("hookshot"));
+        hookshot.click(Click::Left);
+        assert!(hookshot.is_collected("hookshot"));
+    }
+
+    #[test]
+    fn progressive_is_collected_past_first_stage() {
+        let mut bow = item(
+            r#"{
+                type: "progressive",
+                allow_disabled: false,
+                stages: [
+                    { img: "1.png", codes: "bow" },
+                    { img: "2.png", codes: "silver_bow" },
+                ],
+            }"#,
+        );
+
+        assert!(!bow.is_collected("bow"));
+        bow.click(Click::Left);
+        assert!(bow.is_collected("bow"));
+    }
+
+    #[test]
+    fn progressive_is_collected_while_enabled() {
+        let mut sword = item(
+            r#"{
+                type: "progressive",
+                stages: [
+                    { img: "1.png", codes: "sword1" },
+                    { img: "2.png", codes: "sword2" },
+                ],
+            }"#,
+        );
+
+        assert!(!sword.is_collected("sword1"));
+        sword.click(Click::Left);
+        assert!(sword.is_collected("sword1"));
+    }
+
+    #[test]
+    fn progressive_toggle_is_collected_while_active() {
+        let mut bow = item(
+            r#"{
+                type: "progressive_toggle",
+                stages: [
+                    { img: "bow.png", codes: "bow" },
+                    { img: "silver.png", codes: "silver_arrows" },
+                ],
+            }"#,
+        );
+
+        assert!(!bow.is_collected("bow"));
+        bow.click(Click::Right);
+        assert!(!bow.is_collected("bow"));
+        bow.click(Click::Left);
+        assert!(bow.is_collected("bow"));
+    }
+
+    #[test]
+    fn consumable_is_collected_with_quantity() {
+        let mut bombs = item(r#"{ type: "consumable", codes: "bombs", img: "bombs.png" }"#);
+
+        assert!(!bombs.is_collected("bombs"));
+        bombs.click(Click::Left);
+        assert!(bombs.is_collected("bombs"));
+    }
+
+    #[test]
+    fn composite_toggle_is_collected_with_both_parts() {
+        let mut medallions = item(
+            r#"{
+                type: "composite_toggle",
+                codes: "medallions",
+                item_left: "bombos",
+                item_right: "ether",
+                images: [
+                    { left: false, right: false, img: "none.png" },
+                    { left: true, right: false, img: "left.png" },
+                    { left: false, right: true, img: "right.png" },
+                    { left: true, right: true, img: "both.png" },
+                ],
+            }"#,
+        );
+
+        assert!(!medallions.is_collected("medallions"));
+        medallions.click(Click::Left);
+        assert!(!medallions.is_collected("medallions"));
+        medallions.click(Click::Right);
+        assert!(medallions.is_collected("medallions"));
+    }
+}
@@ -0,0 +1,22 @@
+use mlua::{LuaSerdeExt, UserData, UserDataMethods};
+
+use crate::autotracker::VariableStore;
+
+impl UserData for VariableStore {
+    fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("ReadVariable", |lua, this, name: String| {
+            match this.read(&name) {
+                Some(value) => lua.to_value(value),
+                None => Ok(mlua::Value::Nil),
+            }
+        });
+
+        methods.add_meta_method("__index", |_, _, index: mlua::Value| -> mlua::Result<()> {
+            let index = index.to_string()?;
+
+            Err(mlua::Error::runtime(format!(
+                "`VariableStore.{index}` does not exist"
+            )))
+        });
+    }
+}
@@ -0,0 +1,196 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use directories::ProjectDirs;
+use eyre::{eyre, Context, Result};
+use tracing::{debug, instrument};
+
+use crate::pack::{State, VariantUID};
+
+const FILENAME: &str = "autosave.json";
+const DEFAULT_BACKUPS: usize = 5;
+
+/// Directory where tetra-tracker stores user data like autosaves.
+pub fn data_dir() -> Option<PathBuf> {
+    ProjectDirs::from("", "", "tetra-tracker").map(|dirs| dirs.data_dir().to_owned())
+}
+
+/// Autosaves of a single pack variant.
+///
+/// The first save of a session moves the previous autosave into a ring of backups,
+/// all following saves of the session overwrite the current autosave.
+#[derive(Debug)]
+pub struct Autosave {
+    dir: PathBuf,
+    backups: usize,
+    rotated: bool,
+}
+
+impl Autosave {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            backups: DEFAULT_BACKUPS,
+            rotated: false,
+        }
+    }
+
+    /// Autosaves of a pack variant in the user data directory.
+    pub fn for_pack(package_uid: &str, variant_uid: &VariantUID) -> Result<Self> {
+        let data_dir = data_dir().ok_or_else(|| eyre!("failed to find user data directory"))?;
+        let dir = data_dir
+            .join("autosave")
+            .join(sanitize_filename(package_uid))
+            .join(sanitize_filename(variant_uid.as_str()));
+
+        Ok(Self::new(dir))
+    }
+
+    /// Number of backups to keep in addition to the current autosave.
+    pub fn backups(mut self, backups: usize) -> Self {
+        self.backups = backups;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.dir.join(FILENAME)
+    }
+
+    /// Path of the backup with the given number, `1` being the most recent one.
+    pub fn backup_path(&self, number: usize) -> PathBuf {
+        self.dir.join(format!("autosave.{number}.json"))
+    }
+
+    /// Loads the current autosave, if there is one.
+    pub fn load(&self) -> Result<Option<State>> {
+        let path = self.path();
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        State::load(path).map(Some)
+    }
+
+    #[instrument(skip_all, fields(dir = ?self.dir))]
+    pub fn save(&mut self, state: &State) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| eyre!("failed to create autosave directory {:?}", self.dir))?;
+
+        if !self.rotated {
+            self.rotate_backups()?;
+            self.rotated = true;
+        }
+
+        // Write to a temporary file first so a crash while saving can't corrupt the autosave.
+        let path = self.path();
+        let tmp_path = path.with_extension("json.tmp");
+
+        state.save(&tmp_path)?;
+        fs::rename(&tmp_path, &path)
+            .with_context(|| eyre!("failed to move {tmp_path:?} to {path:?}"))?;
+
+        debug!("autosaved");
+
+        Ok(())
+    }
+
+    fn rotate_backups(&self) -> Result<()> {
+        let mut paths = (1..=self.backups)
+            .map(|number| self.backup_path(number))
+            .collect::<Vec<_>>();
+        paths.insert(0, self.path());
+
+        if let Some(oldest_path) = paths.last().filter(|path| path.exists()) {
+            fs::remove_file(oldest_path)
+                .with_context(|| eyre!("failed to remove old backup {oldest_path:?}"))?;
+        }
+
+        for window in paths.windows(2).rev() {
+            let [from, to] = window else {
+                unreachable!();
+            };
+
+            if from.exists() {
+                fs::rename(from, to).with_context(|| eyre!("failed to move {from:?} to {to:?}"))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn sanitize_filename(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::pack::api::tracker::TrackerState;
+    use crate::pack::state::PackInfo;
+
+    use super::*;
+
+    fn state(pin: &str) -> State {
+        let mut tracker_state = TrackerState::default();
+        tracker_state.pins.push(pin.to_owned());
+
+        State::new(
+            PackInfo {
+                uid: "test_pack".into(),
+                variant: VariantUID::from("standard"),
+                version: String::new(),
+            },
+            tracker_state,
+        )
+    }
+
+    #[test]
+    fn save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut autosave = Autosave::new(dir.path());
+
+        assert_eq!(autosave.load().unwrap(), None);
+
+        autosave.save(&state("a")).unwrap();
+        autosave.save(&state("b")).unwrap();
+
+        assert_eq!(autosave.load().unwrap(), Some(state("b")));
+        assert!(!autosave.backup_path(1).exists());
+    }
+
+    #[test]
+    fn rotates_backups_once_per_session() {
+        let dir = tempfile::tempdir().unwrap();
+
+        for pin in ["a", "b", "c", "d"] {
+            let mut autosave = Autosave::new(dir.path()).backups(2);
+
+            autosave.save(&state("ignored")).unwrap();
+            autosave.save(&state(pin)).unwrap();
+        }
+
+        let autosave = Autosave::new(dir.path()).backups(2);
+
+        assert_eq!(autosave.load().unwrap(), Some(state("d")));
+        assert_eq!(State::load(autosave.backup_path(1)).unwrap(), state("c"));
+        assert_eq!(State::load(autosave.backup_path(2)).unwrap(), state("b"));
+        assert!(!autosave.backup_path(3).exists());
+    }
+
+    #[test]
+    fn sanitizes_filenames() {
+        assert_eq!(sanitize_filename("a/b:c"), "a_b_c");
+    }
+}
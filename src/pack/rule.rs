@@ -1,31 +1,41 @@
 use std::fmt;
+use std::iter;
 use std::str::FromStr;
 
 use ariadne::{Color, Label, Report, ReportKind, Source};
 use chumsky::Parser;
 use eyre::eyre;
+use mlua::ErrorContext;
 use mlua::FromLua;
 use mlua::Function;
 use mlua::IntoLua;
 use mlua::Lua;
 use mlua::MultiValue;
+use mlua::Value;
 use serde::de;
 use serde::Deserialize;
 use serde::Serialize;
 
+pub mod evaluator;
 pub mod parser;
 
+pub use evaluator::Evaluator;
+
+use crate::pack::api::ScriptHost;
+
+/// Any character in item codes, call arguments and references can be escaped with `\`,
+/// e.g. `$can_reach|Castle\, Keep`.
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub enum Rule {
     /// rule1,rule1,…
     Multi(Vec<Rule>),
-    /// item_name
-    Item(String),
+    /// item_code or item_code:count
+    Item(ItemCode),
     /// $fn_name|arg1|arg2|…
     Call(Call),
     /// ^$fn_name|arg1|arg2|…
     AccessabilityLevel(Call),
-    /// @location/section
+    /// @location/section, @parent/location/section or @location
     Reference(Reference),
     /// { rule }
     Checkable(Box<Rule>),
@@ -52,7 +62,7 @@ impl fmt::Display for Rule {
                 write!(f, "${name}")?;
 
                 for arg in args {
-                    write!(f, "|{arg}")?;
+                    write!(f, "|{}", parser::escape_arg(arg))?;
                 }
             }
             Rule::AccessabilityLevel(call) => {
@@ -61,13 +71,10 @@ impl fmt::Display for Rule {
                 write!(f, "^${name}")?;
 
                 for arg in args {
-                    write!(f, "|{arg}")?;
+                    write!(f, "|{}", parser::escape_arg(arg))?;
                 }
             }
-            Rule::Reference(reference) => {
-                let Reference { location, section } = reference;
-                write!(f, "@{location}/{section}")?;
-            }
+            Rule::Reference(reference) => reference.fmt(f)?,
             Rule::Checkable(rule) => write!(f, "{{{rule}}}")?,
             Rule::Optional(rule) => write!(f, "[{rule}]")?,
         }
@@ -76,6 +83,18 @@ impl fmt::Display for Rule {
     }
 }
 
+impl Rule {
+    /// All references in the rule, including nested ones.
+    pub fn references(&self) -> Vec<&Reference> {
+        match self {
+            Rule::Multi(rules) => rules.iter().flat_map(Rule::references).collect(),
+            Rule::Reference(reference) => vec![reference],
+            Rule::Checkable(rule) | Rule::Optional(rule) => rule.references(),
+            Rule::Item(_) | Rule::Call(_) | Rule::AccessabilityLevel(_) => Vec::new(),
+        }
+    }
+}
+
 impl FromStr for Rule {
     type Err = eyre::Error;
 
@@ -142,26 +161,272 @@ impl Call {
         R: FromLua,
     {
         let fun = lua.globals().get::<Function>(self.name.as_str())?;
-        let args = self.lua_args(lua)?;
+        let arg_types = ScriptHost::call_arg_types(lua, &self.name)?;
+        let args = self.lua_args(lua, &arg_types)?;
 
         fun.call::<R>(args)
     }
 
-    pub fn lua_args(&self, lua: &Lua) -> mlua::Result<MultiValue> {
+    /// Converts the arguments to the given types.
+    /// Arguments without a type are passed as strings.
+    pub fn lua_args(&self, lua: &Lua, arg_types: &[ArgType]) -> mlua::Result<MultiValue> {
         let mut args = MultiValue::with_capacity(self.args.len());
+        let arg_types = arg_types.iter().chain(iter::repeat(&ArgType::String));
 
-        for arg in &self.args {
-            let lua_arg = arg.as_str().into_lua(lua)?;
+        for (i, (arg, arg_type)) in self.args.iter().zip(arg_types).enumerate() {
+            let lua_arg = arg_type
+                .convert(arg, lua)
+                .with_context(|_| format!("argument {} of `{}`", i + 1, self.name))?;
 
-            args.push_front(lua_arg);
+            args.push_back(lua_arg);
         }
 
         Ok(args)
     }
 }
 
+/// Type of an argument of a [`Call`], declared with `ScriptHost:SetCallArgTypes`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ArgType {
+    String,
+    /// Numeric arguments, e.g. `$has_count|sword|2`.
+    Number,
+}
+
+impl ArgType {
+    fn convert(self, arg: &str, lua: &Lua) -> mlua::Result<Value> {
+        match self {
+            ArgType::String => arg.into_lua(lua),
+            ArgType::Number => arg
+                .trim()
+                .parse()
+                .map(Value::Number)
+                .map_err(|_| mlua::Error::runtime(format!("`{arg}` is not a number"))),
+        }
+    }
+}
+
+impl FromLua for ArgType {
+    fn from_lua(value: Value, _lua: &Lua) -> mlua::Result<Self> {
+        let Value::String(arg_type) = &value else {
+            return Err(mlua::Error::runtime(format!(
+                "invalid argument type: {value:?}"
+            )));
+        };
+
+        match arg_type.to_string_lossy().as_str() {
+            "string" => Ok(ArgType::String),
+            "number" => Ok(ArgType::Number),
+            arg_type => Err(mlua::Error::runtime(format!(
+                "unknown argument type `{arg_type}`"
+            ))),
+        }
+    }
+}
+
+/// Item code that has to be provided, optionally a number of times.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ItemCode {
+    pub code: String,
+    /// Number of times the code has to be provided, once if `None`.
+    pub count: Option<u32>,
+}
+
+impl ItemCode {
+    pub fn required_count(&self) -> u32 {
+        self.count.unwrap_or(1)
+    }
+}
+
+impl fmt::Display for ItemCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ItemCode { code, count } = self;
+
+        write!(f, "{}", parser::escape_item_code(code))?;
+
+        if let Some(count) = count {
+            write!(f, ":{count}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Reference to the accessability of a section or a location.
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct Reference {
-    pub location: String,
-    pub section: String,
+    /// Names of the location and its parents.
+    /// The path may start at any location, not only at top level locations.
+    pub location: Vec<String>,
+    /// Name of the section, `None` for references to a location.
+    ///
+    /// Sections are only parsed from paths with multiple segments, the last of which can also
+    /// name a child location. See [`Tracker::resolve_reference`].
+    ///
+    /// [`Tracker::resolve_reference`]: crate::pack::api::Tracker::resolve_reference
+    pub section: Option<String>,
+}
+
+impl fmt::Display for Reference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Reference { location, section } = self;
+
+        let mut segments = location.iter().chain(section);
+
+        if let Some(segment) = segments.next() {
+            write!(f, "@{}", parser::escape_segment(segment))?;
+        }
+
+        for segment in segments {
+            write!(f, "/{}", parser::escape_segment(segment))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::pack::api::{AccessabilityLevel, Api};
+    use crate::pack::rule::{Evaluator, Rule};
+    use crate::pack::VariantUID;
+
+    /// Api running the script, with a `record` function that stores the types and values of its
+    /// arguments in `received`.
+    fn api(script: &str) -> Api {
+        let api = Api::new("", &VariantUID::from("standard")).unwrap();
+
+        api.lua()
+            .load(
+                r##"
+                function record(...)
+                    received = {}
+
+                    for i = 1, select("#", ...) do
+                        local arg = select(i, ...)
+                        table.insert(received, type(arg) .. " " .. tostring(arg))
+                    end
+
+                    return true
+                end
+                "##,
+            )
+            .exec()
+            .unwrap();
+        api.lua().load(script).exec().unwrap();
+
+        api
+    }
+
+    fn eval(api: &Api, rule: &str) -> AccessabilityLevel {
+        let rule = rule.parse::<Rule>().unwrap();
+
+        api.with_tracker(|tracker| Evaluator::new(tracker, api.lua()).rule(&rule))
+            .unwrap()
+    }
+
+    fn received(api: &Api) -> Option<Vec<String>> {
+        api.lua().load("return received").eval().unwrap()
+    }
+
+    #[test]
+    fn args_are_passed_in_order() {
+        let api = api("");
+
+        assert_eq!(
+            eval(&api, r"$record|a|b\, c||2"),
+            AccessabilityLevel::Normal
+        );
+        assert_eq!(
+            received(&api).unwrap(),
+            ["string a", "string b, c", "string ", "string 2"]
+        );
+    }
+
+    #[test]
+    fn numeric_args() {
+        let api = api("");
+
+        // Without declared types, arguments are passed as written
+        assert_eq!(eval(&api, "$record|007|1e3|+5"), AccessabilityLevel::Normal);
+        assert_eq!(
+            received(&api).unwrap(),
+            ["string 007", "string 1e3", "string +5"]
+        );
+
+        api.lua()
+            .load(r#"ScriptHost:SetCallArgTypes("record", { "number", "string", "number" })"#)
+            .exec()
+            .unwrap();
+
+        assert_eq!(
+            eval(&api, "$record|2|3| -1.5|4"),
+            AccessabilityLevel::Normal
+        );
+        assert_eq!(
+            received(&api).unwrap(),
+            ["number 2", "string 3", "number -1.5", "string 4"]
+        );
+    }
+
+    #[test]
+    fn invalid_number_args() {
+        let api = api(r#"ScriptHost:SetCallArgTypes("record", { "number" })"#);
+
+        assert_eq!(eval(&api, "$record|two"), AccessabilityLevel::None);
+        assert_eq!(received(&api), None);
+    }
+
+    #[test]
+    fn unknown_arg_types() {
+        let api = api("");
+
+        assert!(api
+            .lua()
+            .load(r#"ScriptHost:SetCallArgTypes("record", { "table" })"#)
+            .exec()
+            .is_err());
+    }
+
+    #[test]
+    fn counts() {
+        let api = api(r#"
+            ScriptHost:SetCallArgTypes("count", { "number" })
+
+            function count(n)
+                return n
+            end
+            "#);
+
+        assert_eq!(eval(&api, "$count|0"), AccessabilityLevel::None);
+        assert_eq!(eval(&api, "$count|2"), AccessabilityLevel::Normal);
+        assert_eq!(eval(&api, "$count|0,$record"), AccessabilityLevel::None);
+        assert_eq!(
+            eval(&api, "[$count|0],$record"),
+            AccessabilityLevel::SequenceBreak
+        );
+    }
+
+    #[test]
+    fn accessibility_levels() {
+        let api = api(r#"
+            ScriptHost:SetCallArgTypes("level", { "number" })
+
+            function level(level, name)
+                record(level, name)
+
+                return name and AccessibilityLevel[name] or level
+            end
+            "#);
+
+        assert_eq!(eval(&api, "^$level|3"), AccessabilityLevel::SequenceBreak);
+        assert_eq!(received(&api).unwrap(), ["number 3", "nil nil"]);
+
+        assert_eq!(eval(&api, "^$level|0|Inspect"), AccessabilityLevel::Inspect);
+        assert_eq!(received(&api).unwrap(), ["number 0", "string Inspect"]);
+
+        assert_eq!(eval(&api, "^$level|9"), AccessabilityLevel::None);
+    }
 }
@@ -0,0 +1,661 @@
+use std::cell::RefCell;
+
+use fnv::FnvHashMap;
+use mlua::Lua;
+use tracing::{error, instrument, warn};
+
+use crate::pack::api::tracker::{Location, MapLocation, ReferenceTarget, Section};
+use crate::pack::api::{AccessabilityLevel, Tracker};
+use crate::pack::rule::{Call, Reference, Rule};
+
+/// Evaluates access rules against the current item state of a [`Tracker`].
+///
+/// Levels of referenced locations and sections are cached for the lifetime of the evaluator,
+/// so a new evaluator has to be created whenever the tracker state changes.
+pub struct Evaluator<'a> {
+    tracker: &'a Tracker,
+    lua: &'a Lua,
+    /// `None` marks a location that is currently being evaluated.
+    locations: RefCell<FnvHashMap<String, Option<AccessabilityLevel>>>,
+    /// `None` marks a section that is currently being evaluated.
+    sections: RefCell<FnvHashMap<(String, String), Option<AccessabilityLevel>>>,
+}
+
+impl<'a> Evaluator<'a> {
+    pub fn new(tracker: &'a Tracker, lua: &'a Lua) -> Self {
+        Self {
+            tracker,
+            lua,
+            locations: RefCell::default(),
+            sections: RefCell::default(),
+        }
+    }
+
+    /// Evaluates a list of access rules.
+    /// The list is accessible if any of its rules is accessible.
+    /// An empty list is always accessible.
+    pub fn access_rules(&self, rules: &[Rule]) -> AccessabilityLevel {
+        if rules.is_empty() {
+            return AccessabilityLevel::Normal;
+        }
+
+        rules
+            .iter()
+            .map(|rule| self.rule(rule))
+            .max()
+            .unwrap_or(AccessabilityLevel::None)
+    }
+
+    pub fn rule(&self, rule: &Rule) -> AccessabilityLevel {
+        match rule {
+            Rule::Multi(rules) => rules
+                .iter()
+                .map(|rule| self.rule(rule))
+                .min()
+                .unwrap_or(AccessabilityLevel::Normal),
+            Rule::Item(item) => level_for_count(
+                self.tracker.provider_count_for_item(&item.code),
+                item.required_count(),
+            ),
+            Rule::Call(call) => {
+                level_for_count(self.tracker.provider_count_for_call(self.lua, call), 1)
+            }
+            Rule::AccessabilityLevel(call) => self.call(call),
+            Rule::Reference(reference) => self.reference(reference),
+            Rule::Checkable(rule) => match self.rule(rule) {
+                AccessabilityLevel::None => AccessabilityLevel::None,
+                _ => AccessabilityLevel::Inspect,
+            },
+            Rule::Optional(rule) => self.rule(rule).max(AccessabilityLevel::SequenceBreak),
+        }
+    }
+
+    /// Visibility rules are met unless none of them is accessible.
+    /// An empty list is always met.
+    fn visibility_rules(&self, rules: &[Rule]) -> bool {
+        !self.access_rules(rules).is_none()
+    }
+
+    /// Locations are also hidden if all of their sections are.
+    pub fn location_visible(&self, location: &Location) -> bool {
+        let section_visible = location.sections.is_empty()
+            || location
+                .sections
+                .iter()
+                .any(|section| self.section_visible(section));
+
+        section_visible && self.visibility_rules(&location.visibility_rules)
+    }
+
+    pub fn section_visible(&self, section: &Section) -> bool {
+        self.visibility_rules(&section.visibility_rules)
+    }
+
+    /// Whether the location is visible at the map location.
+    pub fn map_location_visible(&self, location: &Location, map_location: &MapLocation) -> bool {
+        let forced_invisible = !map_location.force_invisibility_rules.is_empty()
+            && self.visibility_rules(&map_location.force_invisibility_rules);
+
+        !forced_invisible
+            && self.location_visible(location)
+            && self.visibility_rules(&map_location.restrict_visibility_rules)
+    }
+
+    /// Accessability of a location, aggregated over all of its visible uncleared sections.
+    /// Locations without visible sections have the accessability of their own access rules.
+    pub fn location(&self, location: &Location) -> AccessabilityLevel {
+        let visible_sections = location
+            .sections
+            .iter()
+            .filter(|section| self.section_visible(section))
+            .collect::<Vec<_>>();
+
+        if visible_sections.is_empty() {
+            return self.location_access_rules(location);
+        }
+
+        let mut levels = visible_sections
+            .into_iter()
+            .filter(|section| !self.tracker.is_section_cleared(section))
+            .map(|section| self.section_access_rules(location, section));
+
+        let Some(first_level) = levels.next() else {
+            return AccessabilityLevel::Cleared;
+        };
+
+        levels.fold(first_level, |aggregated_level, level| {
+            if aggregated_level == level {
+                level
+            } else {
+                AccessabilityLevel::Partial
+            }
+        })
+    }
+
+    /// Accessability of the access rules of a location and all of its parents.
+    /// Child locations are never more accessible than their parents.
+    fn location_access_rules(&self, location: &Location) -> AccessabilityLevel {
+        if let Some(&cached_level) = self.locations.borrow().get(&location.id) {
+            return cached_level.unwrap_or_else(|| {
+                warn!("cyclic reference to `@{}`", location.id);
+                AccessabilityLevel::None
+            });
+        }
+
+        self.locations
+            .borrow_mut()
+            .insert(location.id.clone(), None);
+
+        let mut level = self.access_rules(&location.access_rules);
+
+        if let Some(parent) = self.tracker.parent_location(location) {
+            level = level.min(self.location_access_rules(parent));
+        }
+
+        self.locations
+            .borrow_mut()
+            .insert(location.id.clone(), Some(level));
+
+        level
+    }
+
+    /// Accessability of a section as displayed, [`AccessabilityLevel::Cleared`] once it is cleared.
+    pub fn section(&self, location: &Location, section: &Section) -> AccessabilityLevel {
+        if self.tracker.is_section_cleared(section) {
+            return AccessabilityLevel::Cleared;
+        }
+
+        self.section_access_rules(location, section)
+    }
+
+    /// Accessability of the access rules of a section, taking the rules of its location and its
+    /// parents into account. Cleared sections are evaluated like any other.
+    fn section_access_rules(&self, location: &Location, section: &Section) -> AccessabilityLevel {
+        let key = (
+            location.id.clone(),
+            section.name.clone().unwrap_or_default(),
+        );
+
+        if let Some(&cached_level) = self.sections.borrow().get(&key) {
+            return cached_level.unwrap_or_else(|| {
+                warn!("cyclic reference to `@{}/{}`", key.0, key.1);
+                AccessabilityLevel::None
+            });
+        }
+
+        self.sections.borrow_mut().insert(key.clone(), None);
+
+        let location_level = self.location_access_rules(location);
+        let section_level = self.access_rules(&section.access_rules);
+        let level = location_level.min(section_level);
+
+        self.sections.borrow_mut().insert(key, Some(level));
+
+        level
+    }
+
+    #[instrument(level = "error", skip(self))]
+    fn call(&self, call: &Call) -> AccessabilityLevel {
+        match call.exec::<AccessabilityLevel>(self.lua) {
+            Ok(level) => level,
+            Err(err) => {
+                error!("failed to call `{}`: {err:?}", call.name);
+                AccessabilityLevel::None
+            }
+        }
+    }
+
+    #[instrument(level = "error", skip(self))]
+    fn reference(&self, reference: &Reference) -> AccessabilityLevel {
+        match self.tracker.resolve_reference(reference) {
+            Some(ReferenceTarget::Section(location, section)) => {
+                self.section_access_rules(location, section)
+            }
+            Some(ReferenceTarget::Location(location)) => self.location_access_rules(location),
+            None => {
+                error!("unknown location or section");
+                AccessabilityLevel::None
+            }
+        }
+    }
+}
+
+fn level_for_count(count: i32, required_count: u32) -> AccessabilityLevel {
+    if i64::from(count) >= i64::from(required_count) {
+        AccessabilityLevel::Normal
+    } else {
+        AccessabilityLevel::None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mlua::Lua;
+    use pretty_assertions::assert_eq;
+
+    use crate::pack::api::tracker::{Click, Item, Location};
+    use crate::pack::api::{AccessabilityLevel, Tracker};
+    use crate::pack::rule::Rule;
+    use crate::pack::VariantUID;
+    use crate::util::deserialize_hjson;
+
+    use super::Evaluator;
+
+    fn tracker() -> Tracker {
+        let mut tracker = Tracker::new("", &VariantUID::from("standard"));
+
+        let items = deserialize_hjson::<Vec<Item>>(
+            r#"[
+                { type: "static", name: "Lamp", codes: "lamp", img: "lamp.png" },
+                { type: "static", name: "Sword", codes: "sword", img: "sword.png" },
+            ]"#,
+        )
+        .unwrap();
+
+        let locations = deserialize_hjson::<Vec<Location>>(
+            r#"[
+                {
+                    name: "Cave",
+                    access_rules: ["lamp"],
+                    sections: [
+                        { name: "Front" },
+                        { name: "Back", access_rules: ["hookshot"] },
+                    ],
+                },
+                {
+                    name: "Loop",
+                    sections: [{ name: "Self", access_rules: ["@Loop/Self"] }],
+                },
+                {
+                    name: "Mirror",
+                    access_rules: ["@Mirror"],
+                    sections: [{ name: "Shard" }],
+                },
+            ]"#,
+        )
+        .unwrap();
+
+        tracker.add_items(items);
+        tracker.add_locations(locations);
+
+        tracker
+    }
+
+    #[track_caller]
+    fn eval(rules: &[&str]) -> AccessabilityLevel {
+        eval_with(&tracker(), rules)
+    }
+
+    #[track_caller]
+    fn eval_with(tracker: &Tracker, rules: &[&str]) -> AccessabilityLevel {
+        let lua = Lua::new();
+        let evaluator = Evaluator::new(tracker, &lua);
+        let rules = rules
+            .iter()
+            .map(|rule| rule.parse::<Rule>().unwrap())
+            .collect::<Vec<_>>();
+
+        evaluator.access_rules(&rules)
+    }
+
+    #[test]
+    fn no_rules() {
+        assert_eq!(eval(&[]), AccessabilityLevel::Normal);
+    }
+
+    #[test]
+    fn item() {
+        assert_eq!(eval(&["lamp"]), AccessabilityLevel::Normal);
+        assert_eq!(eval(&["hookshot"]), AccessabilityLevel::None);
+    }
+
+    #[test]
+    fn item_count() {
+        let mut tracker = tracker();
+        let items = deserialize_hjson::<Vec<Item>>(
+            r#"[{ type: "consumable", name: "Bottles", codes: "bottle", img: "bottle.png" }]"#,
+        )
+        .unwrap();
+
+        tracker.add_items(items);
+        tracker.click_item(2, Click::Left);
+
+        assert_eq!(eval_with(&tracker, &["bottle"]), AccessabilityLevel::Normal);
+        assert_eq!(eval_with(&tracker, &["bottle:2"]), AccessabilityLevel::None);
+
+        tracker.click_item(2, Click::Left);
+
+        assert_eq!(
+            eval_with(&tracker, &["bottle:2"]),
+            AccessabilityLevel::Normal
+        );
+        assert_eq!(eval_with(&tracker, &["lamp:2"]), AccessabilityLevel::None);
+    }
+
+    #[test]
+    fn multi_requires_all() {
+        assert_eq!(eval(&["lamp,sword"]), AccessabilityLevel::Normal);
+        assert_eq!(eval(&["lamp,hookshot"]), AccessabilityLevel::None);
+    }
+
+    #[test]
+    fn list_requires_any() {
+        assert_eq!(eval(&["hookshot", "lamp"]), AccessabilityLevel::Normal);
+        assert_eq!(eval(&["hookshot", "flippers"]), AccessabilityLevel::None);
+    }
+
+    #[test]
+    fn optional() {
+        assert_eq!(eval(&["lamp,[sword]"]), AccessabilityLevel::Normal);
+        assert_eq!(
+            eval(&["lamp,[hookshot]"]),
+            AccessabilityLevel::SequenceBreak
+        );
+        assert_eq!(eval(&["hookshot,[lamp]"]), AccessabilityLevel::None);
+    }
+
+    #[test]
+    fn checkable() {
+        assert_eq!(eval(&["{lamp}"]), AccessabilityLevel::Inspect);
+        assert_eq!(eval(&["{hookshot}"]), AccessabilityLevel::None);
+        assert_eq!(eval(&["{hookshot}", "lamp"]), AccessabilityLevel::Normal);
+    }
+
+    #[test]
+    fn reference() {
+        assert_eq!(eval(&["@Cave/Front"]), AccessabilityLevel::Normal);
+        assert_eq!(eval(&["@Cave/Back"]), AccessabilityLevel::None);
+        assert_eq!(eval(&["@Cave/Nowhere"]), AccessabilityLevel::None);
+
+        let mut tracker = tracker();
+
+        tracker.location_mut("Cave").unwrap().sections[1].set_cleared(true);
+
+        // Clearing a section doesn't make it accessible
+        assert_eq!(
+            eval_with(&tracker, &["@Cave/Back"]),
+            AccessabilityLevel::None
+        );
+    }
+
+    #[test]
+    fn cyclic_reference() {
+        assert_eq!(eval(&["@Loop/Self"]), AccessabilityLevel::None);
+        assert_eq!(eval(&["@Mirror"]), AccessabilityLevel::None);
+        assert_eq!(eval(&["@Mirror/Shard"]), AccessabilityLevel::None);
+    }
+
+    #[test]
+    fn location_with_mixed_sections_is_partial() {
+        let lua = Lua::new();
+        let tracker = tracker();
+        let evaluator = Evaluator::new(&tracker, &lua);
+        let cave = tracker.find_location("Cave").unwrap();
+
+        assert_eq!(evaluator.location(cave), AccessabilityLevel::Partial);
+    }
+
+    #[test]
+    fn location_ignores_cleared_sections() {
+        let lua = Lua::new();
+        let mut tracker = tracker();
+
+        tracker.location_mut("Cave").unwrap().sections[1].set_cleared(true);
+
+        let evaluator = Evaluator::new(&tracker, &lua);
+        let cave = tracker.find_location("Cave").unwrap();
+
+        assert_eq!(evaluator.location(cave), AccessabilityLevel::Normal);
+    }
+
+    #[test]
+    fn location_with_all_sections_cleared() {
+        let lua = Lua::new();
+        let mut tracker = tracker();
+
+        tracker.set_sections_cleared(&["Cave/Front".into(), "Cave/Back".into()], true);
+
+        let evaluator = Evaluator::new(&tracker, &lua);
+        let cave = tracker.find_location("Cave").unwrap();
+
+        assert_eq!(evaluator.location(cave), AccessabilityLevel::Cleared);
+        assert_eq!(
+            evaluator.section(cave, &cave.sections[1]),
+            AccessabilityLevel::Cleared
+        );
+        // References still depend on the rules of cleared sections
+        assert_eq!(
+            eval_with(&tracker, &["@Cave/Front"]),
+            AccessabilityLevel::Normal
+        );
+        assert_eq!(
+            eval_with(&tracker, &["@Cave/Back"]),
+            AccessabilityLevel::None
+        );
+    }
+
+    #[test]
+    fn section_with_hosted_item_is_cleared_when_item_is_active() {
+        let lua = Lua::new();
+        let mut tracker = Tracker::new("", &VariantUID::from("standard"));
+        let items = deserialize_hjson::<Vec<Item>>(
+            r#"[{ type: "toggle", name: "Boss", codes: "boss", img: "boss.png" }]"#,
+        )
+        .unwrap();
+        let locations = deserialize_hjson::<Vec<Location>>(
+            r#"[{ name: "Dungeon", sections: [{ name: "Boss", hosted_item: "boss" }] }]"#,
+        )
+        .unwrap();
+
+        tracker.add_items(items);
+        tracker.add_locations(locations);
+
+        let level = |tracker: &Tracker| {
+            let dungeon = tracker.find_location("Dungeon").unwrap();
+
+            Evaluator::new(tracker, &lua).location(dungeon)
+        };
+
+        assert_eq!(level(&tracker), AccessabilityLevel::Normal);
+
+        tracker.click_item(0, Click::Left);
+
+        assert_eq!(level(&tracker), AccessabilityLevel::Cleared);
+
+        // Restoring the section uncollects the hosted item
+        tracker.set_sections_cleared(&["Dungeon/Boss".into()], false);
+
+        assert_eq!(level(&tracker), AccessabilityLevel::Normal);
+        assert!(!tracker.items()[0].is_active());
+
+        tracker.set_sections_cleared(&["Dungeon/Boss".into()], true);
+
+        assert_eq!(level(&tracker), AccessabilityLevel::Cleared);
+        assert!(tracker.items()[0].is_active());
+    }
+
+    #[test]
+    fn visibility_rules() {
+        let lua = Lua::new();
+        let mut tracker = tracker();
+        let locations = deserialize_hjson::<Vec<Location>>(
+            r#"[{
+                name: "Shop",
+                visibility_rules: ["sword"],
+                sections: [
+                    { name: "Item", access_rules: ["hookshot"], visibility_rules: ["shopsanity"] },
+                    { name: "Counter" },
+                ],
+                map_locations: [
+                    { map: "World", x: 0, y: 0, restrict_visibility_rules: ["lamp"] },
+                    { map: "Dungeon", x: 0, y: 0, force_invisibility_rules: ["lamp"] },
+                ],
+            }]"#,
+        )
+        .unwrap();
+
+        tracker.add_locations(locations);
+
+        let evaluator = Evaluator::new(&tracker, &lua);
+        let shop = tracker.find_location("Shop").unwrap();
+
+        assert!(evaluator.location_visible(shop));
+        assert!(!evaluator.section_visible(&shop.sections[0]));
+        // The hidden inaccessible section doesn't make the location partially accessible
+        assert_eq!(evaluator.location(shop), AccessabilityLevel::Normal);
+        assert!(evaluator.map_location_visible(shop, &shop.map_locations[0]));
+        assert!(!evaluator.map_location_visible(shop, &shop.map_locations[1]));
+    }
+
+    #[test]
+    fn location_with_all_sections_hidden() {
+        let lua = Lua::new();
+        let mut tracker = tracker();
+        let locations = deserialize_hjson::<Vec<Location>>(
+            r#"[{
+                name: "Vault",
+                access_rules: ["hookshot"],
+                sections: [{ name: "Gold", visibility_rules: ["shopsanity"] }],
+                map_locations: [{ map: "World", x: 0, y: 0 }],
+            }]"#,
+        )
+        .unwrap();
+
+        tracker.add_locations(locations);
+
+        let evaluator = Evaluator::new(&tracker, &lua);
+        let vault = tracker.find_location("Vault").unwrap();
+
+        assert!(!evaluator.location_visible(vault));
+        assert!(!evaluator.map_location_visible(vault, &vault.map_locations[0]));
+        assert_eq!(evaluator.location(vault), AccessabilityLevel::None);
+    }
+
+    fn dungeon_tracker() -> Tracker {
+        let mut tracker = tracker();
+        let locations = deserialize_hjson::<Vec<Location>>(
+            r#"[
+                {
+                    name: "Tower",
+                    access_rules: ["hookshot"],
+                    children: [{
+                        name: "Floor 1",
+                        sections: [{ name: "Chest" }],
+                        children: [{ name: "Floor 2", sections: [{ name: "Chest" }] }],
+                    }],
+                },
+                {
+                    name: "Castle",
+                    access_rules: ["sword"],
+                    children: [{
+                        name: "Keep",
+                        sections: [{ name: "Chest", access_rules: ["lamp"] }],
+                    }],
+                },
+            ]"#,
+        )
+        .unwrap();
+
+        tracker.add_locations(locations);
+
+        tracker
+    }
+
+    #[test]
+    fn children_inherit_parent_rules() {
+        let lua = Lua::new();
+        let tracker = dungeon_tracker();
+        let evaluator = Evaluator::new(&tracker, &lua);
+        let level = |id: &str| evaluator.location(tracker.location(id).unwrap());
+
+        assert_eq!(level("Tower/Floor 1"), AccessabilityLevel::None);
+        assert_eq!(level("Tower/Floor 1/Floor 2"), AccessabilityLevel::None);
+        assert_eq!(level("Castle/Keep"), AccessabilityLevel::Normal);
+    }
+
+    #[test]
+    fn full_path_references() {
+        let tracker = dungeon_tracker();
+
+        assert_eq!(
+            eval_with(&tracker, &["@Tower/Floor 1/Floor 2/Chest"]),
+            AccessabilityLevel::None
+        );
+        assert_eq!(
+            eval_with(&tracker, &["@Castle/Keep/Chest"]),
+            AccessabilityLevel::Normal
+        );
+        // Child locations can still be referenced by their name
+        assert_eq!(
+            eval_with(&tracker, &["@Keep/Chest"]),
+            AccessabilityLevel::Normal
+        );
+    }
+
+    #[test]
+    fn location_references() {
+        let tracker = dungeon_tracker();
+
+        assert_eq!(
+            eval_with(&tracker, &["@Tower/Floor 1"]),
+            AccessabilityLevel::None
+        );
+        assert_eq!(
+            eval_with(&tracker, &["@Castle"]),
+            AccessabilityLevel::Normal
+        );
+        assert_eq!(eval_with(&tracker, &["@Keep"]), AccessabilityLevel::Normal);
+    }
+
+    #[test]
+    fn unresolved_references() {
+        let mut tracker = dungeon_tracker();
+        let locations = deserialize_hjson::<Vec<Location>>(
+            r#"[{
+                name: "Bridge",
+                access_rules: ["@Castle/Keep/Chest", "@Castle/Moat"],
+                sections: [{ name: "Toll", access_rules: ["[@Tower/Roof/Chest]"] }],
+            }]"#,
+        )
+        .unwrap();
+
+        tracker.add_locations(locations);
+
+        let unresolved = tracker
+            .unresolved_references()
+            .into_iter()
+            .map(|(location_id, reference)| format!("{location_id}: {reference}"))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            unresolved,
+            ["Bridge: @Castle/Moat", "Bridge: @Tower/Roof/Chest"]
+        );
+        assert_eq!(
+            eval_with(&tracker, &["@Castle/Moat"]),
+            AccessabilityLevel::None
+        );
+    }
+
+    #[test]
+    fn unknown_hosted_items() {
+        let lua = Lua::new();
+        let mut tracker = tracker();
+        let locations = deserialize_hjson::<Vec<Location>>(
+            r#"[{ name: "Throne", sections: [{ name: "Crown", hosted_item: "crown" }] }]"#,
+        )
+        .unwrap();
+
+        tracker.add_locations(locations);
+
+        assert_eq!(
+            tracker.unknown_hosted_items(),
+            [("Throne/Crown".to_owned(), "crown".to_owned())]
+        );
+
+        let evaluator = Evaluator::new(&tracker, &lua);
+        let throne = tracker.find_location("Throne").unwrap();
+
+        assert_eq!(evaluator.location(throne), AccessabilityLevel::Normal);
+    }
+}
@@ -2,7 +2,7 @@ use chumsky::prelude::*;
 use chumsky::text::ascii::ident;
 use chumsky::Parser as _;
 
-use crate::pack::rule::{Call, Reference, Rule};
+use crate::pack::rule::{Call, ItemCode, Reference, Rule};
 
 pub trait Parser<'a, T>: chumsky::Parser<'a, &'a str, T, extra::Err<Rich<'a, char>>> {}
 
@@ -11,11 +11,55 @@ impl<'a, T, P> Parser<'a, T> for P where
 {
 }
 
+/// Characters ending call arguments.
+const ARG_TERMINATORS: &str = "\\|,}]";
+/// Characters ending reference segments.
+const SEGMENT_TERMINATORS: &str = "\\/|,}]";
+/// Characters ending item codes and their count.
+const ITEM_TERMINATORS: &str = "\\|,}]:";
+/// Characters that would start another kind of rule at the beginning of an item code.
+const RULE_STARTS: &str = "$^@{[";
+
+/// Escapes the characters that would end call arguments, so [`rule`] parses them as is.
+pub fn escape_arg(arg: &str) -> String {
+    escape(arg, ARG_TERMINATORS)
+}
+
+/// Escapes the characters that would end reference segments, so [`rule`] parses them as is.
+pub fn escape_segment(segment: &str) -> String {
+    escape(segment, SEGMENT_TERMINATORS)
+}
+
+/// Escapes the characters that would end item codes or start other rules,
+/// so [`rule`] parses them as is.
+pub fn escape_item_code(code: &str) -> String {
+    escape(code, &[ITEM_TERMINATORS, RULE_STARTS].concat())
+}
+
+fn escape(text: &str, special: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for char in text.chars() {
+        if special.contains(char) {
+            escaped.push('\\');
+        }
+
+        escaped.push(char);
+    }
+
+    escaped
+}
+
+/// Character of text ending at one of the terminators.
+/// Any character, including terminators, can be escaped with `\`.
+fn text_char<'a>(terminators: &'static str) -> impl Parser<'a, char> + Clone {
+    just('\\').ignore_then(any()).or(none_of(terminators))
+}
+
 pub fn rule<'a>() -> impl Parser<'a, Rule> {
     recursive(|rule| {
-        let string = none_of("|,}]").repeated().to_slice();
-        let arg = just('|').ignore_then(string);
-        let args = arg.map(String::from).repeated().collect();
+        let arg = just('|').ignore_then(text_char(ARG_TERMINATORS).repeated().collect::<String>());
+        let args = arg.repeated().collect();
 
         let call = ident()
             .then(args)
@@ -23,18 +67,24 @@ pub fn rule<'a>() -> impl Parser<'a, Rule> {
                 name: name.into(),
                 args,
             });
-        let rule_call = just("$").ignore_then(call).map(Rule::Call);
+        let rule_call = just("$").ignore_then(call.clone()).map(Rule::Call);
         let rule_accessibility_level = just("^$").ignore_then(call).map(Rule::AccessabilityLevel);
 
-        let reference = none_of("/")
+        let segment = text_char(SEGMENT_TERMINATORS)
             .repeated()
-            .to_slice()
-            .then_ignore(just('/'))
-            .then(string)
-            .map(|(location, section): (&str, &str)| Reference {
-                location: location.into(),
-                section: section.into(),
-            });
+            .at_least(1)
+            .collect::<String>();
+        let reference = segment.separated_by(just('/')).at_least(1).collect().map(
+            |mut location: Vec<String>| {
+                let section = if location.len() > 1 {
+                    location.pop()
+                } else {
+                    None
+                };
+
+                Reference { location, section }
+            },
+        );
         let rule_reference = just("@").ignore_then(reference).map(Rule::Reference);
 
         let rule_checkable = rule
@@ -49,9 +99,14 @@ pub fn rule<'a>() -> impl Parser<'a, Rule> {
             .map(Box::new)
             .map(Rule::Optional);
 
-        let rule_item = string
-            .filter(|item: &&str| !item.is_empty())
-            .map(String::from)
+        let rule_item = text_char(ITEM_TERMINATORS)
+            .repeated()
+            .collect::<String>()
+            .separated_by(just(':'))
+            .at_least(1)
+            .collect()
+            .map(item_code)
+            .filter(|item: &ItemCode| !item.code.is_empty())
             .map(Rule::Item);
 
         choice((
@@ -75,10 +130,42 @@ pub fn rule<'a>() -> impl Parser<'a, Rule> {
     .then_ignore(end())
 }
 
+/// Joins the segments of an item code split at unescaped `:`.
+/// A last segment of digits is the count, e.g. `bottle:2`.
+fn item_code(segments: Vec<String>) -> ItemCode {
+    let code_and_count = segments.split_last().and_then(|(count, code)| {
+        let code = code.join(":");
+        let is_count = !count.is_empty() && count.bytes().all(|byte| byte.is_ascii_digit());
+
+        if code.is_empty() || !is_count {
+            return None;
+        }
+
+        Some((code, count.parse().ok()?))
+    });
+
+    match code_and_count {
+        Some((code, count)) => ItemCode {
+            code,
+            count: Some(count),
+        },
+        None => ItemCode {
+            code: segments.join(":"),
+            count: None,
+        },
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::test_helpers::*;
     use pretty_assertions::assert_eq;
+    use proptest::collection::vec;
+    use proptest::option;
+    use proptest::prelude::*;
+    use proptest::string::string_regex;
+
+    use crate::pack::rule::{Call, ItemCode, Reference, Rule};
 
     #[test]
     fn call_without_args() {
@@ -104,7 +191,7 @@ mod tests {
     fn checkable_reference() {
         assert_eq!(
             parse("{@location/section}"),
-            checkable(reference("location", "section")),
+            checkable(reference(["location"], Some("section"))),
         );
     }
 
@@ -127,7 +214,10 @@ mod tests {
     fn multi_checkable() {
         assert_eq!(
             parse("{@foo/bar,$call|me}"),
-            checkable(multi([reference("foo", "bar"), call("call", ["me"]),])),
+            checkable(multi([
+                reference(["foo"], Some("bar")),
+                call("call", ["me"]),
+            ])),
         );
     }
 
@@ -135,7 +225,10 @@ mod tests {
     fn multi_optional() {
         assert_eq!(
             parse("[@foo/bar,$call|me]"),
-            optional(multi([reference("foo", "bar"), call("call", ["me"]),])),
+            optional(multi([
+                reference(["foo"], Some("bar")),
+                call("call", ["me"]),
+            ])),
         );
     }
 
@@ -144,21 +237,142 @@ mod tests {
         assert_eq!(
             parse("{@foo/bar,$call|me},[$hello|world,@x/y]"),
             multi([
-                checkable(multi([reference("foo", "bar"), call("call", ["me"]),])),
-                optional(multi([call("hello", ["world"]), reference("x", "y"),]))
+                checkable(multi([
+                    reference(["foo"], Some("bar")),
+                    call("call", ["me"]),
+                ])),
+                optional(multi([
+                    call("hello", ["world"]),
+                    reference(["x"], Some("y")),
+                ]))
             ])
         );
     }
 
     #[test]
     fn single_reference() {
-        assert_eq!(parse("@location/section"), reference("location", "section"));
+        assert_eq!(
+            parse("@location/section"),
+            reference(["location"], Some("section"))
+        );
+    }
+
+    #[test]
+    fn location_reference() {
+        assert_eq!(parse("@Hyrule Castle"), reference(["Hyrule Castle"], None));
+    }
+
+    #[test]
+    fn nested_reference() {
+        assert_eq!(
+            parse("@Dark World/Pyramid/Ledge,sword"),
+            multi([
+                reference(["Dark World", "Pyramid"], Some("Ledge")),
+                item("sword")
+            ])
+        );
+    }
+
+    #[test]
+    fn empty_reference_segment() {
+        assert!("@Dark World//Ledge".parse::<Rule>().is_err());
     }
 
     #[test]
     fn single_item() {
         assert_eq!(parse("item"), item("item"));
     }
+
+    #[test]
+    fn item_with_count() {
+        assert_eq!(
+            parse("bottle:2,key:x"),
+            multi([item_count("bottle", 2), item("key:x")])
+        );
+        assert_eq!(item_count("bottle", 2).to_string(), "bottle:2");
+    }
+
+    #[test]
+    fn escaped_call_args() {
+        assert_eq!(
+            parse(r"$can_reach|Castle\, Keep|a\|b\]\\,{$x|\}}"),
+            multi([
+                call("can_reach", ["Castle, Keep", "a|b]\\"]),
+                checkable(call("x", ["}"])),
+            ])
+        );
+        assert_eq!(
+            call("can_reach", ["Castle, Keep", "a|b]\\"]).to_string(),
+            r"$can_reach|Castle\, Keep|a\|b\]\\"
+        );
+    }
+
+    #[test]
+    fn escaped_items_and_references() {
+        assert_eq!(
+            parse(r"\$coin\:2:3,@Dark\/World/Pyramid\, Ledge"),
+            multi([
+                item_count("$coin:2", 3),
+                reference(["Dark/World"], Some("Pyramid, Ledge")),
+            ])
+        );
+        assert_eq!(item_count("$coin:2", 3).to_string(), r"\$coin\:2:3");
+    }
+
+    proptest! {
+        #[test]
+        fn display_round_trip(rule in rules()) {
+            prop_assert_eq!(parse(&rule.to_string()), rule);
+        }
+    }
+
+    /// Rules as they are parsed, i.e. without single rules or directly nested rules in
+    /// [`Rule::Multi`].
+    fn rules() -> impl Strategy<Value = Rule> {
+        leaf_rules().prop_recursive(4, 32, 4, |inner| {
+            let wrapped = prop_oneof![
+                inner
+                    .clone()
+                    .prop_map(|rule| Rule::Checkable(Box::new(rule))),
+                inner.prop_map(|rule| Rule::Optional(Box::new(rule))),
+            ];
+
+            prop_oneof![
+                wrapped.clone(),
+                vec(prop_oneof![leaf_rules(), wrapped], 2..4).prop_map(Rule::Multi),
+            ]
+        })
+    }
+
+    fn leaf_rules() -> impl Strategy<Value = Rule> {
+        let reference = prop_oneof![
+            text(1).prop_map(|location| Reference {
+                location: vec![location],
+                section: None,
+            }),
+            (vec(text(1), 1..3), text(1)).prop_map(|(location, section)| Reference {
+                location,
+                section: Some(section),
+            }),
+        ];
+
+        prop_oneof![
+            (text(1), option::of(any::<u32>()))
+                .prop_map(|(code, count)| Rule::Item(ItemCode { code, count })),
+            calls().prop_map(Rule::Call),
+            calls().prop_map(Rule::AccessabilityLevel),
+            reference.prop_map(Rule::Reference),
+        ]
+    }
+
+    fn calls() -> impl Strategy<Value = Call> {
+        ("[a-z_]{1,8}", vec(text(0), 0..3)).prop_map(|(name, args)| Call { name, args })
+    }
+
+    /// Text of up to 8 characters, mostly those with a special meaning in rules.
+    fn text(min_len: usize) -> impl Strategy<Value = String> {
+        string_regex(&format!(r"[a-z0-9 \\|,{{}}\[\]:/$^@]{{{min_len},8}}")).unwrap()
+    }
 }
 
 #[cfg(test)]
@@ -166,16 +380,19 @@ pub mod test_helpers {
     use ariadne::{Color, Label, Report, ReportKind, Source};
     use chumsky::Parser as _;
 
-    use super::{rule, Call, Reference, Rule};
+    use super::{rule, Call, ItemCode, Reference, Rule};
 
     pub fn checkable(rule: Rule) -> Rule {
         Rule::Checkable(Box::new(rule))
     }
 
-    pub fn reference(location: &str, section: &str) -> Rule {
+    pub fn reference<'a>(
+        location: impl IntoIterator<Item = &'a str>,
+        section: Option<&str>,
+    ) -> Rule {
         Rule::Reference(Reference {
-            location: location.into(),
-            section: section.into(),
+            location: location.into_iter().map(String::from).collect(),
+            section: section.map(String::from),
         })
     }
 
@@ -186,8 +403,18 @@ pub mod test_helpers {
         })
     }
 
-    pub fn item(name: &str) -> Rule {
-        Rule::Item(name.into())
+    pub fn item(code: &str) -> Rule {
+        Rule::Item(ItemCode {
+            code: code.into(),
+            count: None,
+        })
+    }
+
+    pub fn item_count(code: &str, count: u32) -> Rule {
+        Rule::Item(ItemCode {
+            code: code.into(),
+            count: Some(count),
+        })
     }
 
     pub fn optional(rule: Rule) -> Rule {
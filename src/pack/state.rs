@@ -0,0 +1,197 @@
+use std::fs;
+use std::path::Path;
+
+use eyre::{bail, eyre, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::pack::api::tracker::TrackerState;
+use crate::pack::VariantUID;
+
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Saved state of a pack, laid out like PopTracker's state files so that saves can be moved
+/// between both.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct State {
+    #[serde(default = "default_format_version")]
+    pub format_version: u32,
+    pub pack: PackInfo,
+    #[serde(rename = "tracker_state")]
+    pub state: TrackerState,
+}
+
+fn default_format_version() -> u32 {
+    FORMAT_VERSION
+}
+
+impl State {
+    pub fn new(pack: PackInfo, state: TrackerState) -> Self {
+        Self {
+            format_version: FORMAT_VERSION,
+            pack,
+            state,
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let data = fs::read_to_string(path)
+            .with_context(|| eyre!("failed to read state: {}", path.display()))?;
+        let state = serde_json::from_str::<State>(&data)
+            .with_context(|| eyre!("failed to parse state: {}", path.display()))?;
+
+        if state.format_version > FORMAT_VERSION {
+            bail!(
+                "state format version {} is newer than the supported version {FORMAT_VERSION}",
+                state.format_version
+            );
+        }
+
+        Ok(state)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let data = serde_json::to_string_pretty(self).context("failed to serialize state")?;
+
+        fs::write(path, data).with_context(|| eyre!("failed to write state: {}", path.display()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PackInfo {
+    pub uid: String,
+    pub variant: VariantUID,
+    #[serde(default)]
+    pub version: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use pretty_assertions::assert_eq;
+    use serde_json::{json, Value};
+
+    use crate::pack::api::tracker::state::SectionState;
+    use crate::pack::test_helpers::fixture_pack;
+
+    use super::*;
+
+    #[test]
+    fn json_layout() {
+        let mut tracker_state = TrackerState::default();
+        tracker_state
+            .items
+            .insert("1".into(), json!({ "stage1": 1, "stage2": 0, "count": 0 }));
+        tracker_state
+            .sections
+            .insert("Cave/Front".into(), SectionState { cleared: 1 });
+        tracker_state.pins.push("Cave".into());
+
+        let state = State::new(
+            PackInfo {
+                uid: "test_pack".into(),
+                variant: VariantUID::from("standard"),
+                version: "1.0".into(),
+            },
+            tracker_state,
+        );
+
+        let json = serde_json::to_value(&state).unwrap();
+
+        assert_eq!(
+            json,
+            json!({
+                "format_version": 1,
+                "pack": { "uid": "test_pack", "variant": "standard", "version": "1.0" },
+                "tracker_state": {
+                    "items": { "1": { "stage1": 1, "stage2": 0, "count": 0 } },
+                    "locations": { "Cave/Front": { "cleared": 1 } },
+                    "pins": ["Cave"],
+                    "notes": {},
+                },
+            })
+        );
+
+        let loaded_state = serde_json::from_value::<State>(json).unwrap();
+
+        assert_eq!(loaded_state, state);
+    }
+
+    #[test]
+    fn poptracker_round_trip() {
+        let path =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/poptracker_state.json");
+        let pack = fixture_pack("watches");
+
+        pack.load_state_from(&path).unwrap();
+
+        let (active, cleared) = pack
+            .api
+            .with_tracker(|tracker| {
+                let active = tracker
+                    .items()
+                    .iter()
+                    .map(|item| item.is_active())
+                    .collect::<Vec<_>>();
+                let cleared = tracker.location("Cave").unwrap().sections[0].chests_cleared();
+
+                (active, cleared)
+            })
+            .unwrap();
+
+        assert_eq!(active, [true, false]);
+        assert!(cleared);
+
+        let dir = tempfile::tempdir().unwrap();
+        let saved_path = dir.path().join("state.json");
+
+        pack.save_state_to(&saved_path).unwrap();
+
+        let read_json = |path: &Path| {
+            serde_json::from_str::<Value>(&fs::read_to_string(path).unwrap()).unwrap()
+        };
+        let original = read_json(&path);
+        let saved = read_json(&saved_path);
+
+        assert_eq!(saved["pack"], original["pack"]);
+        assert_eq!(
+            saved["tracker_state"]["locations"],
+            original["tracker_state"]["locations"]
+        );
+
+        // PopTracker saves more item fields, like overlays, which are dropped
+        for id in ["1", "2"] {
+            for field in ["stage1", "stage2", "count"] {
+                assert_eq!(
+                    saved["tracker_state"]["items"][id][field],
+                    original["tracker_state"]["items"][id][field]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn newer_versions_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        let mut state = State::new(
+            PackInfo {
+                uid: "test_pack".into(),
+                variant: VariantUID::from("standard"),
+                version: "1.0".into(),
+            },
+            TrackerState::default(),
+        );
+
+        state.save(&path).unwrap();
+
+        assert_eq!(State::load(&path).unwrap(), state);
+
+        state.format_version = FORMAT_VERSION + 1;
+        state.save(&path).unwrap();
+
+        assert!(State::load(&path).is_err());
+    }
+}
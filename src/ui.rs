@@ -1,11 +1,21 @@
+mod action;
+mod archipelago;
+mod auto_tracker;
+mod item_button;
 mod location_button;
 mod location_popup;
 mod pack_picker;
+mod settings;
 mod tracker;
 
+pub use action::Action;
+pub use archipelago::Archipelago;
+pub use auto_tracker::AutoTracker;
+pub use item_button::ItemButton;
 pub use location_button::LocationButton;
 pub use location_popup::LocationPopup;
 pub use pack_picker::PackPicker;
+pub use settings::{Palette, Settings};
 pub use tracker::Tracker;
 
 pub mod image {
@@ -0,0 +1,10 @@
+use crate::pack::api::tracker::Click;
+
+/// Changes to the tracker state requested while rendering.
+pub enum Action {
+    ClickItem { index: usize, click: Click },
+    ClickSectionChest { section: String, click: Click },
+    ToggleLocationCleared { location: String },
+    ToggleLocationPinned { location: String },
+    SetLocationNote { location: String, note: String },
+}
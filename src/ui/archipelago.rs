@@ -0,0 +1,120 @@
+use egui::{Color32, Grid, TextEdit, Ui};
+use tracing::info;
+
+use crate::archipelago::protocol::ClientPacket;
+use crate::archipelago::{Client, ConnectSettings, Event};
+
+/// Archipelago connection settings and status.
+#[derive(Default)]
+pub struct Archipelago {
+    settings: ConnectSettings,
+    client: Option<Client>,
+    status: Status,
+}
+
+#[derive(Default)]
+enum Status {
+    #[default]
+    Disconnected,
+    Connecting,
+    Connected,
+    Error(String),
+}
+
+impl Archipelago {
+    pub fn is_connected(&self) -> bool {
+        matches!(self.status, Status::Connected)
+    }
+
+    pub fn send(&self, packet: ClientPacket) {
+        if let Some(client) = &self.client {
+            client.send(packet);
+        }
+    }
+
+    pub fn connect(&mut self) {
+        info!(server = self.settings.server, "connecting to archipelago");
+
+        self.client = Some(Client::connect(self.settings.clone()));
+        self.status = Status::Connecting;
+    }
+
+    pub fn disconnect(&mut self) {
+        self.client = None;
+        self.status = Status::Disconnected;
+    }
+
+    /// Returns all events received since the last poll and updates the connection status.
+    pub fn poll(&mut self) -> Vec<Event> {
+        let Some(client) = &self.client else {
+            return Vec::new();
+        };
+
+        let events = client.poll();
+
+        for event in &events {
+            match event {
+                Event::Connected { .. } => self.status = Status::Connected,
+                Event::Refused { errors } => {
+                    self.status = Status::Error(format!("refused: {}", errors.join(", ")));
+                }
+                Event::Disconnected { error } => {
+                    self.client = None;
+
+                    if let Some(error) = error {
+                        self.status = Status::Error(error.clone());
+                    } else if !matches!(self.status, Status::Error(_)) {
+                        self.status = Status::Disconnected;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        events
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui) {
+        let editable = self.client.is_none();
+
+        Grid::new("archipelago_settings")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Server");
+                ui.add_enabled(
+                    editable,
+                    TextEdit::singleline(&mut self.settings.server)
+                        .hint_text("archipelago.gg:38281"),
+                );
+                ui.end_row();
+
+                ui.label("Slot");
+                ui.add_enabled(editable, TextEdit::singleline(&mut self.settings.slot));
+                ui.end_row();
+
+                ui.label("Password");
+                ui.add_enabled(
+                    editable,
+                    TextEdit::singleline(&mut self.settings.password).password(true),
+                );
+                ui.end_row();
+            });
+
+        ui.horizontal(|ui| {
+            if self.client.is_none() {
+                if ui.button("Connect").clicked() {
+                    self.connect();
+                }
+            } else if ui.button("Disconnect").clicked() {
+                self.disconnect();
+            }
+
+            match &self.status {
+                Status::Disconnected => ui.label("Disconnected"),
+                Status::Connecting => ui.label("Connecting…"),
+                Status::Connected => ui.colored_label(Color32::GREEN, "Connected"),
+                Status::Error(error) => ui.colored_label(Color32::RED, error),
+            };
+        });
+    }
+}
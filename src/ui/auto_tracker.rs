@@ -0,0 +1,164 @@
+use egui::{Color32, DragValue, Grid, Ui};
+use tracing::{error, info};
+
+use crate::autotracker::lua_connector::{self, LuaConnector};
+use crate::autotracker::uat::{self, Uat};
+use crate::autotracker::usb2snes::{self, Usb2Snes};
+use crate::autotracker::{Backend, Status, ThreadedBackend};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BackendKind {
+    Usb2Snes,
+    LuaConnector,
+    Uat,
+}
+
+enum Connection {
+    /// Backend for memory watches.
+    Memory(ThreadedBackend),
+    /// Server for variable watches.
+    Uat(Uat),
+}
+
+/// Autotracking backend settings and status.
+pub struct AutoTracker {
+    kind: BackendKind,
+    usb2snes_url: String,
+    lua_connector_port: u16,
+    uat_url: String,
+    connection: Option<Connection>,
+    /// Error of the last failed attempt to connect.
+    error: Option<String>,
+}
+
+impl Default for AutoTracker {
+    fn default() -> Self {
+        Self {
+            kind: BackendKind::Usb2Snes,
+            usb2snes_url: usb2snes::DEFAULT_URL.into(),
+            lua_connector_port: lua_connector::DEFAULT_PORT,
+            uat_url: uat::DEFAULT_URL.into(),
+            connection: None,
+            error: None,
+        }
+    }
+}
+
+impl AutoTracker {
+    pub fn backend(&self) -> Option<&dyn Backend> {
+        match &self.connection {
+            Some(Connection::Memory(backend)) => Some(backend),
+            _ => None,
+        }
+    }
+
+    pub fn uat(&self) -> Option<&Uat> {
+        match &self.connection {
+            Some(Connection::Uat(uat)) => Some(uat),
+            _ => None,
+        }
+    }
+
+    pub fn status(&self) -> Status {
+        match (&self.connection, &self.error) {
+            (Some(Connection::Memory(backend)), _) => backend.status(),
+            (Some(Connection::Uat(uat)), _) => uat.status(),
+            (None, Some(error)) => Status::Error(error.clone()),
+            (None, None) => Status::Disconnected,
+        }
+    }
+
+    fn connect(&mut self) {
+        self.error = None;
+
+        match self.kind {
+            BackendKind::Usb2Snes => {
+                info!(url = self.usb2snes_url, "connecting to usb2snes");
+                let backend = Usb2Snes::backend(&self.usb2snes_url);
+                self.connection = Some(Connection::Memory(backend));
+            }
+            BackendKind::LuaConnector => {
+                info!(port = self.lua_connector_port, "waiting for Lua connector");
+
+                match LuaConnector::backend(("127.0.0.1", self.lua_connector_port)) {
+                    Ok(backend) => self.connection = Some(Connection::Memory(backend)),
+                    Err(err) => {
+                        error!("{err:?}");
+                        self.error = Some(format!("{err:#}"));
+                    }
+                }
+            }
+            BackendKind::Uat => {
+                info!(url = self.uat_url, "connecting to UAT");
+                self.connection = Some(Connection::Uat(Uat::connect(&self.uat_url)));
+            }
+        }
+    }
+
+    /// Shows the settings. Returns `true` if the backend changed.
+    pub fn ui(&mut self, ui: &mut Ui) -> bool {
+        let mut changed = false;
+
+        ui.add_enabled_ui(self.connection.is_none(), |ui| {
+            Grid::new("auto_tracker_settings")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.radio_value(&mut self.kind, BackendKind::Usb2Snes, "usb2snes");
+                    ui.text_edit_singleline(&mut self.usb2snes_url);
+                    ui.end_row();
+
+                    ui.radio_value(&mut self.kind, BackendKind::LuaConnector, "Lua connector");
+                    ui.add(DragValue::new(&mut self.lua_connector_port).prefix("port "));
+                    ui.end_row();
+
+                    ui.radio_value(&mut self.kind, BackendKind::Uat, "UAT");
+                    ui.text_edit_singleline(&mut self.uat_url);
+                    ui.end_row();
+                });
+        });
+
+        ui.horizontal(|ui| {
+            if self.connection.is_none() {
+                if ui.button("Connect").clicked() {
+                    self.connect();
+                    changed = true;
+                }
+            } else if ui.button("Disconnect").clicked() {
+                self.connection = None;
+                changed = true;
+            }
+
+            let status = self.status();
+
+            ui.colored_label(status_color(&status), status_text(status));
+        });
+
+        changed
+    }
+
+    /// Shows a colored dot for the connection status, with details on hover.
+    pub fn status_indicator(&self, ui: &mut Ui) {
+        let status = self.status();
+
+        ui.colored_label(status_color(&status), "⏺")
+            .on_hover_text(status_text(status));
+    }
+}
+
+fn status_color(status: &Status) -> Color32 {
+    match status {
+        Status::Disconnected => Color32::GRAY,
+        Status::Connecting => Color32::YELLOW,
+        Status::Connected { .. } => Color32::GREEN,
+        Status::Error(_) => Color32::RED,
+    }
+}
+
+fn status_text(status: Status) -> String {
+    match status {
+        Status::Disconnected => "Disconnected".into(),
+        Status::Connecting => "Connecting…".into(),
+        Status::Connected { device } => device,
+        Status::Error(error) => error,
+    }
+}
@@ -0,0 +1,89 @@
+use std::path::Path;
+
+use egui::{Align2, Color32, FontId, Image, Response, Sense, Ui, Vec2, Widget};
+
+use crate::pack::api::tracker::{Click, Display, StatefulItem};
+
+pub struct ItemButton<'a> {
+    root: &'a Path,
+    item: &'a StatefulItem,
+    size: f32,
+}
+
+impl<'a> ItemButton<'a> {
+    pub fn new(root: &'a Path, item: &'a StatefulItem) -> Self {
+        Self {
+            root,
+            item,
+            size: 32.,
+        }
+    }
+
+    pub fn size(mut self, size: f32) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// The item click corresponding to the mouse button that clicked the response.
+    pub fn click(response: &Response) -> Option<Click> {
+        if response.clicked() {
+            Some(Click::Left)
+        } else if response.secondary_clicked() {
+            Some(Click::Right)
+        } else if response.middle_clicked() {
+            Some(Click::Middle)
+        } else {
+            None
+        }
+    }
+}
+
+impl Widget for ItemButton<'_> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let (rect, response) = ui.allocate_exact_size(Vec2::splat(self.size), Sense::click());
+
+        if !ui.is_rect_visible(rect) {
+            return response;
+        }
+
+        let active = self.item.is_active();
+        let (img, img_mods, greyed_out) = match self.item.display() {
+            Some(Display {
+                disabled_img: Some(disabled_img),
+                disabled_img_mods,
+                ..
+            }) if !active => (Some(disabled_img), disabled_img_mods, false),
+            Some(Display { img, img_mods, .. }) => (Some(img), img_mods, !active),
+            None => (None, &None, false),
+        };
+        let greyed_out = greyed_out
+            || img_mods
+                .as_deref()
+                .is_some_and(|img_mods| img_mods.contains("@disabled"));
+
+        let tint = if greyed_out {
+            Color32::from_gray(70)
+        } else {
+            Color32::WHITE
+        };
+
+        let icon = self.item.icon();
+        let Some(img) = icon.as_deref().or(img.map(String::as_str)) else {
+            return response.on_hover_text(self.item.name().into_owned());
+        };
+        let img_path = format!("file://{}", self.root.join(img).display());
+        Image::new(img_path).tint(tint).paint_at(ui, rect);
+
+        if let Some(count) = self.item.count() {
+            ui.painter().text(
+                rect.right_bottom(),
+                Align2::RIGHT_BOTTOM,
+                count.to_string(),
+                FontId::proportional(self.size / 2.5),
+                Color32::WHITE,
+            );
+        }
+
+        response.on_hover_text(self.item.name().into_owned())
+    }
+}
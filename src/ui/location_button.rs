@@ -2,16 +2,30 @@ use egui::{popup, Color32, PopupCloseBehavior, Rounding, Sense, Stroke, Ui, Vec2
 use tracing::trace;
 
 use crate::pack::api::tracker::{Location, MapLocation};
-use crate::ui::LocationPopup;
+use crate::pack::api::Tracker;
+use crate::pack::rule::Evaluator;
+use crate::ui::{Action, LocationPopup};
 
 pub struct LocationButton<'a> {
     popup_id: egui::Id,
     location: &'a Location,
     map_location: &'a MapLocation,
+    fill_color: Color32,
+    tracker: &'a Tracker,
+    evaluator: &'a Evaluator<'a>,
+    actions: &'a mut Vec<Action>,
 }
 
 impl<'a> LocationButton<'a> {
-    pub fn new(ui: &Ui, location: &'a Location, map_location: &'a MapLocation) -> Self {
+    pub fn new(
+        ui: &Ui,
+        location: &'a Location,
+        map_location: &'a MapLocation,
+        fill_color: Color32,
+        tracker: &'a Tracker,
+        evaluator: &'a Evaluator<'a>,
+        actions: &'a mut Vec<Action>,
+    ) -> Self {
         Self {
             popup_id: ui.make_persistent_id((
                 &map_location.map,
@@ -21,6 +35,10 @@ impl<'a> LocationButton<'a> {
             )),
             location,
             map_location,
+            fill_color,
+            tracker,
+            evaluator,
+            actions,
         }
     }
 }
@@ -51,7 +69,7 @@ impl<'a> Widget for LocationButton<'a> {
         ui.painter().rect(
             rect,
             Rounding::ZERO,
-            Color32::GREEN,
+            self.fill_color,
             Stroke::new(2., outline_color),
         );
 
@@ -65,8 +83,15 @@ impl<'a> Widget for LocationButton<'a> {
                 &response,
                 PopupCloseBehavior::CloseOnClickOutside,
                 |ui| {
-                    ui.scope(|ui| ui.add(LocationPopup::new(self.location)))
-                        .response
+                    ui.scope(|ui| {
+                        ui.add(LocationPopup::new(
+                            self.location,
+                            self.tracker,
+                            self.evaluator,
+                            self.actions,
+                        ))
+                    })
+                    .response
                 },
             );
 
@@ -1,40 +1,147 @@
-use egui::{Image, Response, ScrollArea, Ui, Vec2, Widget};
+use egui::{Image, ImageSource, Response, ScrollArea, Sense, TextEdit, Ui, Vec2, Widget};
 
-use crate::pack::api::tracker::Location;
-use crate::ui::image;
+use crate::pack::api::tracker::{section_id, Location, Section};
+use crate::pack::api::Tracker;
+use crate::pack::rule::Evaluator;
+use crate::ui::{image, Action, ItemButton};
 
 pub struct LocationPopup<'a> {
     location: &'a Location,
+    tracker: &'a Tracker,
+    evaluator: &'a Evaluator<'a>,
+    actions: &'a mut Vec<Action>,
 }
 
 impl<'a> LocationPopup<'a> {
-    pub fn new(location: &'a Location) -> Self {
-        Self { location }
+    pub fn new(
+        location: &'a Location,
+        tracker: &'a Tracker,
+        evaluator: &'a Evaluator<'a>,
+        actions: &'a mut Vec<Action>,
+    ) -> Self {
+        Self {
+            location,
+            tracker,
+            evaluator,
+            actions,
+        }
+    }
+}
+
+impl LocationPopup<'_> {
+    /// Shows the chests and the hosted item of the section.
+    /// Clicking a chest clears or restores one chest, the hosted item is clicked like in layouts.
+    fn show_section(&mut self, ui: &mut Ui, section: &Section) {
+        let chest_count = if section.clear_as_group {
+            section.item_count().min(1)
+        } else {
+            section.item_count()
+        };
+
+        for index in 0..chest_count {
+            let opened = if section.clear_as_group {
+                section.chests_cleared()
+            } else {
+                index < section.cleared
+            };
+            let chest = Image::new(self.chest_image(section, opened))
+                .max_size(Vec2::splat(25.))
+                .fit_to_original_size(1.)
+                .sense(Sense::click());
+
+            if let Some(click) = ItemButton::click(&ui.add(chest)) {
+                self.actions.push(Action::ClickSectionChest {
+                    section: section_id(&self.location.id, section),
+                    click,
+                });
+            }
+        }
+
+        if let Some(code) = &section.hosted_item {
+            self.show_hosted_item(ui, code);
+        }
+
+        if section.item_count() > 0 {
+            ui.label(format!("{}/{}", section.remaining(), section.item_count()));
+        }
+    }
+
+    fn show_hosted_item(&mut self, ui: &mut Ui, code: &str) {
+        let Some((index, item)) = self.tracker.find_item_for_code(code) else {
+            return;
+        };
+        let response = ui.add(ItemButton::new(self.tracker.root(), item).size(25.));
+
+        if let Some(click) = ItemButton::click(&response) {
+            self.actions.push(Action::ClickItem { index, click });
+        }
+    }
+
+    fn chest_image(&self, section: &Section, opened: bool) -> ImageSource<'static> {
+        let (img, default_img) = if opened {
+            (&section.chest_opened_img, image::OPEN)
+        } else {
+            (&section.chest_unopened_img, image::CLOSED)
+        };
+
+        match img {
+            Some(img) => {
+                let img_path = format!("file://{}", self.tracker.root().join(img).display());
+
+                ImageSource::Uri(img_path.into())
+            }
+            None => default_img,
+        }
     }
 }
 
 impl Widget for LocationPopup<'_> {
-    fn ui(self, ui: &mut Ui) -> Response {
+    fn ui(mut self, ui: &mut Ui) -> Response {
         ScrollArea::vertical()
             .max_height(ui.available_height())
             .show(ui, |ui| {
                 ui.set_min_width(150.);
                 ui.vertical(|ui| {
-                    ui.strong(&self.location.name);
+                    ui.horizontal(|ui| {
+                        ui.strong(&self.location.name);
+
+                        let pinned = self.tracker.is_location_pinned(&self.location.id);
+
+                        if ui.selectable_label(pinned, "📌").clicked() {
+                            self.actions.push(Action::ToggleLocationPinned {
+                                location: self.location.id.clone(),
+                            });
+                        }
+                    });
                     // ui.label(format!("{:#?}", self.location.access_rules));
 
-                    for section in &self.location.sections {
+                    let location = self.location;
+
+                    let sections = location
+                        .sections
+                        .iter()
+                        .filter(|section| self.evaluator.section_visible(section));
+
+                    for section in sections {
                         if let Some(name) = &section.name {
                             ui.strong(name);
                         }
 
                         // ui.label(format!("{:#?}", section.access_rules));
 
-                        ui.add(
-                            Image::new(image::CLOSED)
-                                .max_size(Vec2::splat(25.))
-                                .fit_to_original_size(1.),
-                        );
+                        ui.horizontal(|ui| self.show_section(ui, section));
+                    }
+
+                    let mut note = self.tracker.location_note(&self.location.id).to_owned();
+                    let note_edit = TextEdit::multiline(&mut note)
+                        .hint_text("Notes")
+                        .desired_rows(1);
+
+                    if ui.add(note_edit).changed() {
+                        self.actions.push(Action::SetLocationNote {
+                            location: self.location.id.clone(),
+                            note,
+                        });
                     }
                 })
             })
@@ -0,0 +1,99 @@
+use std::time::Duration;
+
+use egui::{Color32, DragValue, Grid, Ui};
+
+use crate::pack::api::AccessabilityLevel;
+
+pub struct Settings {
+    pub palette: Palette,
+    pub hide_cleared_locations: bool,
+    /// Prefer the horizontal layout of the pack if it has one.
+    pub horizontal_layout: bool,
+    /// Time the frame handlers of the pack may take per frame.
+    pub frame_handler_budget: Duration,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            palette: Palette::default(),
+            hide_cleared_locations: false,
+            horizontal_layout: false,
+            frame_handler_budget: Duration::from_millis(5),
+        }
+    }
+}
+
+impl Settings {
+    pub fn ui(&mut self, ui: &mut Ui) {
+        ui.checkbox(&mut self.hide_cleared_locations, "Hide cleared locations");
+        ui.checkbox(&mut self.horizontal_layout, "Horizontal layout");
+
+        ui.horizontal(|ui| {
+            let mut budget_ms = self.frame_handler_budget.as_millis() as u64;
+
+            ui.label("Frame handler budget");
+            ui.add(DragValue::new(&mut budget_ms).range(1..=100).suffix(" ms"));
+
+            self.frame_handler_budget = Duration::from_millis(budget_ms);
+        });
+
+        ui.separator();
+        self.palette.ui(ui);
+    }
+}
+
+/// Colors of map locations by accessability level.
+pub struct Palette {
+    pub none: Color32,
+    pub partial: Color32,
+    pub inspect: Color32,
+    pub sequence_break: Color32,
+    pub normal: Color32,
+    pub cleared: Color32,
+}
+
+impl Palette {
+    pub fn color(&self, level: AccessabilityLevel) -> Color32 {
+        match level {
+            AccessabilityLevel::None => self.none,
+            AccessabilityLevel::Partial => self.partial,
+            AccessabilityLevel::Inspect => self.inspect,
+            AccessabilityLevel::SequenceBreak => self.sequence_break,
+            AccessabilityLevel::Normal => self.normal,
+            AccessabilityLevel::Cleared => self.cleared,
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui) {
+        Grid::new("palette").num_columns(2).show(ui, |ui| {
+            let colors = [
+                ("None", &mut self.none),
+                ("Partial", &mut self.partial),
+                ("Inspect", &mut self.inspect),
+                ("Sequence break", &mut self.sequence_break),
+                ("Normal", &mut self.normal),
+                ("Cleared", &mut self.cleared),
+            ];
+
+            for (label, color) in colors {
+                ui.label(label);
+                ui.color_edit_button_srgba(color);
+                ui.end_row();
+            }
+        });
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            none: Color32::from_rgb(0xff, 0x30, 0x30),
+            partial: Color32::from_rgb(0xff, 0xff, 0x30),
+            inspect: Color32::from_rgb(0x60, 0x60, 0xff),
+            sequence_break: Color32::from_rgb(0xff, 0xa0, 0x30),
+            normal: Color32::GREEN,
+            cleared: Color32::from_rgb(0x50, 0x50, 0x50),
+        }
+    }
+}
@@ -1,34 +1,130 @@
 use std::ops::ControlFlow;
+use std::time::{Duration, Instant};
 
 use egui::Button;
-use egui::SizeHint;
-use egui::TextureOptions;
-use egui::{Image, Rect, Vec2};
-use tracing::error;
+use egui::{Image, Vec2, ViewportBuilder, ViewportId};
+use eyre::Result;
+use tracing::{error, info};
 
-use crate::pack::{self, Pack};
+use crate::pack::api::tracker::layout::{BROADCAST_LAYOUT, DEFAULT_LAYOUT, HORIZONTAL_LAYOUT};
+use crate::pack::autosave::Autosave;
+use crate::pack::{self, Pack, State};
 use crate::ui::image;
-use crate::ui::LocationButton;
+use crate::ui::{Action, Archipelago, AutoTracker, Settings};
+
+use layout::LayoutRenderer;
+
+mod layout;
 
 pub struct Tracker {
     pack: Pack,
     current_map: usize,
+    settings: Settings,
+    show_settings: bool,
+    show_broadcast: bool,
+    archipelago: Archipelago,
+    show_archipelago: bool,
+    auto_tracker: AutoTracker,
+    show_auto_tracker: bool,
+    autosave: Option<Autosave>,
+    last_autosave: Instant,
+    last_frame: Instant,
+    last_autosaved_state: Option<State>,
+    /// Autosave of the previous session the user can choose to restore.
+    /// Autosaving is paused until the user decided.
+    restore_offer: Option<State>,
 }
 
+const STATE_FILE_EXTENSION: &str = "json";
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(10);
+/// How often memory and variable watches are checked while autotracking.
+const AUTOTRACKING_INTERVAL: Duration = Duration::from_millis(50);
+/// How often frame handlers are called while nothing else causes a repaint.
+const FRAME_HANDLER_INTERVAL: Duration = Duration::from_millis(16);
+
 impl Tracker {
     pub fn new(pack: Pack) -> Self {
+        let autosave = pack
+            .api
+            .with_tracker(|tracker| {
+                Autosave::for_pack(&pack.manifest.package_uid, tracker.variant_uid())
+            })
+            .and_then(|autosave| autosave)
+            .inspect_err(|err| error!("autosave disabled: {err:?}"))
+            .ok();
+
         Self {
             pack,
             current_map: 0,
+            settings: Settings::default(),
+            show_settings: false,
+            show_broadcast: false,
+            archipelago: Archipelago::default(),
+            show_archipelago: false,
+            auto_tracker: AutoTracker::default(),
+            show_auto_tracker: false,
+            autosave,
+            last_autosave: Instant::now(),
+            last_frame: Instant::now(),
+            last_autosaved_state: None,
+            restore_offer: None,
+        }
+    }
+
+    /// Offers to restore the last autosaved session of the pack variant.
+    pub fn offer_restore(mut self) -> Self {
+        let Some(autosave) = &self.autosave else {
+            return self;
+        };
+
+        match autosave.load() {
+            Ok(state) => self.restore_offer = state,
+            Err(err) => error!("failed to load autosave: {err:?}"),
         }
+
+        self
     }
 
     pub fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) -> ControlFlow<()> {
         let mut control_flow = ControlFlow::Continue(());
+        let mut actions = Vec::new();
+        let mut import_state = false;
+        let mut export_state = false;
+
+        egui::Window::new("Settings")
+            .open(&mut self.show_settings)
+            .show(ctx, |ui| self.settings.ui(ui));
+
+        egui::Window::new("Archipelago")
+            .open(&mut self.show_archipelago)
+            .show(ctx, |ui| self.archipelago.ui(ui));
+
+        let mut auto_tracker_changed = false;
+
+        egui::Window::new("Autotracking")
+            .open(&mut self.show_auto_tracker)
+            .show(ctx, |ui| auto_tracker_changed = self.auto_tracker.ui(ui));
+
+        if auto_tracker_changed {
+            if let Err(err) = self.pack.api.reset_memory_watches() {
+                error!("{err:?}");
+            }
+
+            if let Err(err) = self.pack.api.reset_variable_watches() {
+                error!("{err:?}");
+            }
+        }
+
+        self.show_restore_offer(ctx);
+        self.handle_archipelago_events();
+        self.update_memory_watches(ctx);
+        self.update_variable_watches(ctx);
+        self.run_frame_handlers(ctx);
 
+        let lua = self.pack.api.lua();
         let result = self.pack.api.with_tracker(|tracker| {
-            egui::CentralPanel::default().show(ctx, |ui| {
-                ui.vertical(|ui| {
+            egui::TopBottomPanel::top("toolbar").show(ctx, |ui| {
+                ui.horizontal(|ui| {
                     let load_image = Image::new(image::LOAD).max_size(Vec2::splat(20.));
                     let load_button = Button::image(load_image);
 
@@ -36,99 +132,313 @@ impl Tracker {
                         control_flow = ControlFlow::Break(());
                     }
 
-                    ui.horizontal_wrapped(|ui| {
-                        for (i, map) in tracker.maps().iter().enumerate() {
-                            ui.selectable_value(&mut self.current_map, i, &map.name);
-                        }
-                    });
+                    if ui.button("⚙").clicked() {
+                        self.show_settings = !self.show_settings;
+                    }
+
+                    let archipelago_label = if self.archipelago.is_connected() {
+                        "AP ✔"
+                    } else {
+                        "AP"
+                    };
+
+                    ui.toggle_value(&mut self.show_archipelago, archipelago_label);
+                    ui.toggle_value(&mut self.show_auto_tracker, "Autotracking");
+
+                    self.auto_tracker.status_indicator(ui);
+
+                    import_state = ui.button("Import").clicked();
+                    export_state = ui.button("Export").clicked();
+
+                    if tracker.layout(BROADCAST_LAYOUT).is_some() {
+                        ui.toggle_value(&mut self.show_broadcast, "Broadcast");
+                    }
                 });
+            });
+
+            let mut renderer =
+                LayoutRenderer::new(&self.pack.root, lua, tracker, &self.settings, &mut actions);
 
-                // // Preload all images
-                // for map in tracker.maps() {
-                //     let map_image_path =
-                //         format!("file://{}", self.pack.root.join(&map.img).display());
-
-                //     ImageSource::Uri(map_image_path.into()).load(
-                //         ctx,
-                //         TextureOptions::default(),
-                //         SizeHint::default(),
-                //     );
-                // }
-
-                if let Some(map) = tracker.maps().get(self.current_map) {
-                    let map_image_path =
-                        format!("file://{}", self.pack.root.join(&map.img).display());
-                    let map_image = Image::new(map_image_path);
-                    let map_image_size = map_image
-                        .source(ctx)
-                        .load(ctx, TextureOptions::default(), SizeHint::default())
-                        .map(|texture_poll| texture_poll.size())
-                        .unwrap_or(None);
-
-                    let map_image_resp = ui.add(map_image);
-                    let map_widget_rect = map_image_resp.rect;
-
-                    Self::add_locations(
-                        ctx,
-                        ui,
-                        tracker,
-                        map_widget_rect,
-                        map_image_size,
-                        self.current_map,
-                    );
+            let layout_key = if self.settings.horizontal_layout {
+                HORIZONTAL_LAYOUT
+            } else {
+                DEFAULT_LAYOUT
+            };
+            let layout = tracker
+                .layout(layout_key)
+                .or_else(|| tracker.layout(DEFAULT_LAYOUT));
+
+            match layout {
+                Some(layout) => {
+                    egui::CentralPanel::default().show(ctx, |ui| renderer.show(ui, layout));
                 }
-            });
+                None => {
+                    Self::show_without_layout(ctx, tracker, &mut renderer, &mut self.current_map)
+                }
+            }
+
+            let broadcast_layout = tracker.layout(BROADCAST_LAYOUT);
+
+            if let (true, Some(broadcast_layout)) = (self.show_broadcast, broadcast_layout) {
+                ctx.show_viewport_immediate(
+                    ViewportId::from_hash_of(BROADCAST_LAYOUT),
+                    ViewportBuilder::default().with_title("Broadcast"),
+                    |ctx, _class| {
+                        egui::CentralPanel::default()
+                            .show(ctx, |ui| renderer.show(ui, broadcast_layout));
+
+                        if ctx.input(|input| input.viewport().close_requested()) {
+                            self.show_broadcast = false;
+                        }
+                    },
+                );
+            }
         });
 
         if let Err(err) = result {
             error!("failed to access tracker: {err:?}");
         }
 
+        self.apply_actions(actions);
+
+        if import_state {
+            self.import_state();
+        }
+
+        if export_state {
+            self.export_state();
+        }
+
+        if let Err(err) = self.pack.api.dispatch_tracker_changes() {
+            error!("{err:?}");
+        }
+
+        if self.last_autosave.elapsed() >= AUTOSAVE_INTERVAL {
+            self.autosave();
+        }
+
+        ctx.request_repaint_after(AUTOSAVE_INTERVAL);
+
         control_flow
     }
 
-    fn add_locations(
-        _ctx: &egui::Context,
-        ui: &mut egui::Ui,
-        tracker: &pack::api::Tracker,
-        map_widget_rect: Rect,
-        map_image_size: Option<Vec2>,
-        current_map: usize,
-    ) {
-        let current_map = tracker
-            .maps()
-            .get(current_map)
-            .map(|map| map.name.as_str())
-            .unwrap_or_default();
-
-        // let current_map_name = tra
-        let Some(map_image_size) = map_image_size else {
-            error!("map image size unknown!");
+    fn update_memory_watches(&self, ctx: &egui::Context) {
+        let Some(backend) = self.auto_tracker.backend() else {
+            return;
+        };
+
+        if let Err(err) = self.pack.api.update_memory_watches(backend, Instant::now()) {
+            error!("{err:?}");
+        }
+
+        ctx.request_repaint_after(AUTOTRACKING_INTERVAL);
+    }
+
+    fn update_variable_watches(&self, ctx: &egui::Context) {
+        let Some(uat) = self.auto_tracker.uat() else {
+            return;
+        };
+
+        if let Err(err) = self.pack.api.update_variable_watches(uat, Instant::now()) {
+            error!("{err:?}");
+        }
+
+        ctx.request_repaint_after(AUTOTRACKING_INTERVAL);
+    }
+
+    fn run_frame_handlers(&mut self, ctx: &egui::Context) {
+        let now = Instant::now();
+        let elapsed = now - self.last_frame;
+        self.last_frame = now;
+
+        let budget = self.settings.frame_handler_budget;
+
+        match self.pack.api.run_frame_handlers(elapsed, budget) {
+            // Keep updating, handlers are used for timers and animations
+            Ok(true) => ctx.request_repaint_after(FRAME_HANDLER_INTERVAL),
+            Ok(false) => {}
+            Err(err) => error!("{err:?}"),
+        }
+    }
+
+    fn handle_archipelago_events(&mut self) {
+        for event in self.archipelago.poll() {
+            if let Err(err) = self.pack.api.handle_archipelago_event(&event) {
+                error!("{err:?}");
+            }
+        }
+
+        match self.pack.api.take_archipelago_packets() {
+            Ok(packets) => {
+                for packet in packets {
+                    self.archipelago.send(packet);
+                }
+            }
+            Err(err) => error!("{err:?}"),
+        }
+    }
+
+    fn show_restore_offer(&mut self, ctx: &egui::Context) {
+        let Some(state) = &self.restore_offer else {
+            return;
+        };
+
+        let mut restore = None;
+
+        egui::Window::new("Restore session")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label("Restore the last session of this pack?");
+
+                ui.horizontal(|ui| {
+                    if ui.button("Restore").clicked() {
+                        restore = Some(true);
+                    }
+
+                    if ui.button("Discard").clicked() {
+                        restore = Some(false);
+                    }
+                });
+            });
+
+        let Some(restore) = restore else {
             return;
         };
 
-        let Vec2 {
-            x: width,
-            y: height,
-        } = map_image_size;
+        if restore {
+            match self.pack.load_state(state) {
+                Ok(()) => info!("restored last session"),
+                Err(err) => error!("failed to restore last session: {err:?}"),
+            }
+        }
+
+        self.restore_offer = None;
+    }
+
+    /// Saves the current state if it changed since the last autosave.
+    pub fn autosave(&mut self) {
+        self.last_autosave = Instant::now();
+
+        if self.restore_offer.is_some() {
+            return;
+        }
+
+        let Some(autosave) = &mut self.autosave else {
+            return;
+        };
+
+        let result = (|| -> Result<()> {
+            let state = self.pack.state()?;
+
+            if self.last_autosaved_state.as_ref() == Some(&state) {
+                return Ok(());
+            }
 
-        for location in tracker.locations_recursive() {
-            for map_location in &location.map_locations {
-                if map_location.map != current_map {
-                    continue;
+            autosave.save(&state)?;
+            self.last_autosaved_state = Some(state);
+
+            Ok(())
+        })();
+
+        if let Err(err) = result {
+            error!("failed to autosave: {err:?}");
+        }
+    }
+
+    fn import_state(&self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("State", &[STATE_FILE_EXTENSION])
+            .pick_file()
+        else {
+            return;
+        };
+
+        if let Err(err) = self.pack.load_state_from(&path) {
+            error!("failed to import state from {path:?}: {err:?}");
+        }
+    }
+
+    fn export_state(&self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("State", &[STATE_FILE_EXTENSION])
+            .set_file_name(format!(
+                "{}.{STATE_FILE_EXTENSION}",
+                self.pack.manifest.package_uid
+            ))
+            .save_file()
+        else {
+            return;
+        };
+
+        if let Err(err) = self.pack.save_state_to(&path) {
+            error!("failed to export state to {path:?}: {err:?}");
+        }
+    }
+
+    /// Shows all items and a map selector for packs without a layout.
+    fn show_without_layout(
+        ctx: &egui::Context,
+        tracker: &pack::api::Tracker,
+        renderer: &mut LayoutRenderer,
+        current_map: &mut usize,
+    ) {
+        egui::SidePanel::left("items").show(ctx, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                for (index, item) in tracker.items().iter().enumerate() {
+                    renderer.item_button(ui, index, item, 32.);
                 }
+            });
+        });
 
-                let x = map_location.x as f32 / width * map_widget_rect.width();
-                let y = map_location.y as f32 / height * map_widget_rect.height();
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                for (i, map) in tracker.maps().iter().enumerate() {
+                    ui.selectable_value(current_map, i, &map.name);
+                }
+            });
+
+            if let Some(map) = tracker.maps().get(*current_map) {
+                renderer.show_map(ui, map);
+            }
+        });
+    }
+
+    fn apply_actions(&mut self, actions: Vec<Action>) {
+        if actions.is_empty() {
+            return;
+        }
 
-                let button_rect = Rect {
-                    min: map_widget_rect.min + Vec2::new(x, y) - Vec2::splat(5.),
-                    max: map_widget_rect.min + Vec2::new(x, y) + Vec2::splat(5.),
-                };
+        let api = &self.pack.api;
 
-                let location_button = LocationButton::new(ui, location, map_location);
-                ui.put(button_rect, location_button);
+        for action in actions {
+            let result = match action {
+                // Not borrowing the tracker, as the callbacks of Lua items may access it
+                Action::ClickItem { index, click } => api.click_item(index, click).map(|_| ()),
+                Action::ClickSectionChest { section, click } => {
+                    api.with_tracker_mut(|tracker| tracker.click_section_chest(&section, click))
+                }
+                Action::ToggleLocationCleared { location } => {
+                    api.toggle_location_cleared(&location)
+                }
+                Action::ToggleLocationPinned { location } => {
+                    api.with_tracker_mut(|tracker| tracker.toggle_location_pinned(&location))
+                }
+                Action::SetLocationNote { location, note } => {
+                    api.with_tracker_mut(|tracker| tracker.set_location_note(&location, note))
+                }
+            };
+
+            if let Err(err) = result {
+                error!("failed to access tracker: {err:?}");
             }
         }
     }
 }
+
+impl Drop for Tracker {
+    fn drop(&mut self) {
+        self.autosave();
+    }
+}
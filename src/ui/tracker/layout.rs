@@ -0,0 +1,376 @@
+use std::path::Path;
+
+use egui::{
+    CentralPanel, Color32, Frame, Grid, Image, Rect, SidePanel, SizeHint, TextureOptions,
+    TopBottomPanel, Ui, Vec2,
+};
+use hex_color::HexColor;
+use mlua::Lua;
+use tracing::error;
+
+use crate::pack::api::tracker::layout::{self, DockSide, Layout, Orientation, Variant};
+use crate::pack::api::tracker::{Map, StatefulItem};
+use crate::pack::api::Tracker;
+use crate::pack::rule::Evaluator;
+use crate::ui::{Action, ItemButton, LocationButton, LocationPopup, Settings};
+
+/// Maximum nesting of layout references, protects against reference cycles.
+const MAX_REFERENCE_DEPTH: usize = 32;
+
+const DEFAULT_ITEM_SIZE: f32 = 32.;
+
+/// Renders pack layouts and collects the actions triggered by them.
+pub struct LayoutRenderer<'a> {
+    root: &'a Path,
+    tracker: &'a Tracker,
+    evaluator: Evaluator<'a>,
+    settings: &'a Settings,
+    actions: &'a mut Vec<Action>,
+    reference_depth: usize,
+}
+
+impl<'a> LayoutRenderer<'a> {
+    pub fn new(
+        root: &'a Path,
+        lua: &'a Lua,
+        tracker: &'a Tracker,
+        settings: &'a Settings,
+        actions: &'a mut Vec<Action>,
+    ) -> Self {
+        Self {
+            root,
+            tracker,
+            evaluator: Evaluator::new(tracker, lua),
+            settings,
+            actions,
+            reference_depth: 0,
+        }
+    }
+
+    pub fn show(&mut self, ui: &mut Ui, layout: &Layout) {
+        let layout::Common {
+            background,
+            dock: _,
+            margin,
+            max_width,
+            max_height,
+        } = &layout.common;
+
+        let mut frame = Frame::none();
+
+        if let Some(background) = background {
+            let HexColor { r, g, b, a } = *background;
+
+            frame = frame.fill(Color32::from_rgba_unmultiplied(r, g, b, a));
+        }
+
+        if let Some(margin) = margin {
+            frame = frame.inner_margin(egui::Margin {
+                left: margin.left,
+                right: margin.right,
+                top: margin.top,
+                bottom: margin.bottom,
+            });
+        }
+
+        frame.show(ui, |ui| {
+            if let Some(max_width) = max_width {
+                ui.set_max_width(*max_width as f32);
+            }
+
+            if let Some(max_height) = max_height {
+                ui.set_max_height(*max_height as f32);
+            }
+
+            self.show_variant(ui, &layout.variant);
+        });
+    }
+
+    fn show_variant(&mut self, ui: &mut Ui, variant: &Variant) {
+        match variant {
+            Variant::Container(container) => self.show_all(ui, &container.content),
+            Variant::Dock(dock) => self.show_dock(ui, &dock.content),
+            Variant::Array(array) => match array.orientation {
+                Orientation::Horizontal => {
+                    ui.horizontal(|ui| self.show_all(ui, &array.content));
+                }
+                Orientation::Vertical => {
+                    ui.vertical(|ui| self.show_all(ui, &array.content));
+                }
+            },
+            Variant::Tabbed(tabbed) => self.show_tabbed(ui, tabbed),
+            Variant::Group(group) => self.show_group(ui, group),
+            Variant::Map(map) => self.show_maps(ui, &map.maps),
+            Variant::ItemGrid(item_grid) => self.show_item_grid(ui, item_grid),
+            Variant::Item(item) => {
+                let size = item.item_size.map_or(DEFAULT_ITEM_SIZE, |size| size.width);
+
+                self.show_item(ui, &item.item, size);
+            }
+            Variant::RecentPins(recent_pins) => match recent_pins.orientation {
+                Orientation::Horizontal => {
+                    ui.horizontal(|ui| self.show_pinned_locations(ui));
+                }
+                Orientation::Vertical => {
+                    ui.vertical(|ui| self.show_pinned_locations(ui));
+                }
+            },
+            Variant::Layout(reference) => self.show_reference(ui, &reference.key),
+            Variant::Unknown => {}
+        }
+    }
+
+    fn show_all(&mut self, ui: &mut Ui, layouts: &[Layout]) {
+        for layout in layouts {
+            self.show(ui, layout);
+        }
+    }
+
+    fn show_dock(&mut self, ui: &mut Ui, layouts: &[Layout]) {
+        let mut undocked_layouts = Vec::new();
+
+        for (index, layout) in layouts.iter().enumerate() {
+            let id = ui.id().with(("dock", index));
+
+            match layout.common.dock {
+                Some(DockSide::Left) => {
+                    SidePanel::left(id).show_inside(ui, |ui| self.show(ui, layout));
+                }
+                Some(DockSide::Right) => {
+                    SidePanel::right(id).show_inside(ui, |ui| self.show(ui, layout));
+                }
+                Some(DockSide::Top) => {
+                    TopBottomPanel::top(id).show_inside(ui, |ui| self.show(ui, layout));
+                }
+                Some(DockSide::Bottom) => {
+                    TopBottomPanel::bottom(id).show_inside(ui, |ui| self.show(ui, layout));
+                }
+                None => undocked_layouts.push(layout),
+            }
+        }
+
+        CentralPanel::default()
+            .frame(Frame::none())
+            .show_inside(ui, |ui| {
+                for layout in undocked_layouts {
+                    self.show(ui, layout);
+                }
+            });
+    }
+
+    fn show_tabbed(&mut self, ui: &mut Ui, tabbed: &layout::Tabbed) {
+        let titles = tabbed
+            .tabs
+            .iter()
+            .map(|tab| tab.title.as_str())
+            .collect::<Vec<_>>();
+        let id = ui.make_persistent_id(&titles);
+        let mut current_tab = ui.data_mut(|data| *data.get_persisted_mut_or_default::<usize>(id));
+
+        ui.horizontal_wrapped(|ui| {
+            for (index, tab) in tabbed.tabs.iter().enumerate() {
+                if let Some(icon) = &tab.icon {
+                    let icon_path = format!("file://{}", self.root.join(icon).display());
+
+                    ui.add(Image::new(icon_path).max_size(Vec2::splat(16.)));
+                }
+
+                ui.selectable_value(&mut current_tab, index, &tab.title);
+            }
+        });
+
+        ui.data_mut(|data| data.insert_persisted(id, current_tab));
+
+        if let Some(tab) = tabbed.tabs.get(current_tab) {
+            self.show_all(ui, &tab.content);
+        }
+    }
+
+    fn show_group(&mut self, ui: &mut Ui, group: &layout::Group) {
+        Frame::group(ui.style()).show(ui, |ui| {
+            ui.vertical(|ui| {
+                if !group.header.is_empty() {
+                    ui.strong(&group.header);
+                }
+
+                self.show_all(ui, &group.content);
+            });
+        });
+    }
+
+    fn show_reference(&mut self, ui: &mut Ui, key: &str) {
+        let Some(layout) = self.tracker.layout(key) else {
+            error!("unknown layout `{key}`");
+            return;
+        };
+
+        if self.reference_depth >= MAX_REFERENCE_DEPTH {
+            error!("layout references nested too deeply at `{key}`");
+            return;
+        }
+
+        self.reference_depth += 1;
+        self.show(ui, layout);
+        self.reference_depth -= 1;
+    }
+
+    fn show_pinned_locations(&mut self, ui: &mut Ui) {
+        for location_id in self.tracker.pinned_locations() {
+            let Some(location) = self.tracker.location(location_id) else {
+                continue;
+            };
+
+            if !self.evaluator.location_visible(location) {
+                continue;
+            }
+
+            Frame::group(ui.style()).show(ui, |ui| {
+                ui.add(LocationPopup::new(
+                    location,
+                    self.tracker,
+                    &self.evaluator,
+                    self.actions,
+                ));
+            });
+        }
+    }
+
+    fn show_item_grid(&mut self, ui: &mut Ui, item_grid: &layout::ItemGrid) {
+        let item_size = item_grid
+            .item_size
+            .map_or(DEFAULT_ITEM_SIZE, |size| size.width);
+        let spacing = item_grid
+            .item_margin
+            .map_or(ui.spacing().item_spacing, |margin| {
+                Vec2::new(margin.width, margin.height)
+            });
+
+        Grid::new(&item_grid.rows).spacing(spacing).show(ui, |ui| {
+            for row in &item_grid.rows {
+                for code in row {
+                    self.show_item(ui, code, item_size);
+                }
+
+                ui.end_row();
+            }
+        });
+    }
+
+    fn show_item(&mut self, ui: &mut Ui, code: &str, size: f32) {
+        match self.tracker.find_item_for_code(code) {
+            Some((index, item)) => self.item_button(ui, index, item, size),
+            None => {
+                ui.allocate_space(Vec2::splat(size));
+            }
+        }
+    }
+
+    pub fn item_button(&mut self, ui: &mut Ui, index: usize, item: &StatefulItem, size: f32) {
+        let response = ui.add(ItemButton::new(self.root, item).size(size));
+
+        if let Some(click) = ItemButton::click(&response) {
+            self.actions.push(Action::ClickItem { index, click });
+        }
+    }
+
+    fn show_maps(&mut self, ui: &mut Ui, map_names: &[String]) {
+        let maps = map_names
+            .iter()
+            .filter_map(|map_name| {
+                let map = self.tracker.find_map(map_name);
+
+                if map.is_none() {
+                    error!("unknown map `{map_name}`");
+                }
+
+                map
+            })
+            .collect::<Vec<_>>();
+
+        match maps.as_slice() {
+            [] => {}
+            [map] => self.show_map(ui, map),
+            _ => ui.columns(maps.len(), |columns| {
+                for (ui, map) in columns.iter_mut().zip(&maps) {
+                    self.show_map(ui, map);
+                }
+            }),
+        }
+    }
+
+    pub fn show_map(&mut self, ui: &mut Ui, map: &Map) {
+        let ctx = ui.ctx().clone();
+        let map_image_path = format!("file://{}", self.root.join(&map.img).display());
+        let map_image = Image::new(map_image_path);
+        let map_image_size = map_image
+            .source(&ctx)
+            .load(&ctx, TextureOptions::default(), SizeHint::default())
+            .map(|texture_poll| texture_poll.size())
+            .unwrap_or(None);
+
+        let map_image_resp = ui.add(map_image);
+        let map_widget_rect = map_image_resp.rect;
+
+        self.add_locations(ui, map, map_widget_rect, map_image_size);
+    }
+
+    fn add_locations(
+        &mut self,
+        ui: &mut Ui,
+        map: &Map,
+        map_widget_rect: Rect,
+        map_image_size: Option<Vec2>,
+    ) {
+        let Some(map_image_size) = map_image_size else {
+            error!("map image size unknown!");
+            return;
+        };
+
+        let Vec2 {
+            x: width,
+            y: height,
+        } = map_image_size;
+
+        for location in self.tracker.locations_recursive() {
+            let level = self.evaluator.location(location);
+
+            if level.is_cleared() && self.settings.hide_cleared_locations {
+                continue;
+            }
+
+            let fill_color = self.settings.palette.color(level);
+
+            for map_location in &location.map_locations {
+                if map_location.map != map.name
+                    || !self.evaluator.map_location_visible(location, map_location)
+                {
+                    continue;
+                }
+
+                let x = map_location.x as f32 / width * map_widget_rect.width();
+                let y = map_location.y as f32 / height * map_widget_rect.height();
+
+                let button_rect = Rect {
+                    min: map_widget_rect.min + Vec2::new(x, y) - Vec2::splat(5.),
+                    max: map_widget_rect.min + Vec2::new(x, y) + Vec2::splat(5.),
+                };
+
+                let location_button = LocationButton::new(
+                    ui,
+                    location,
+                    map_location,
+                    fill_color,
+                    self.tracker,
+                    &self.evaluator,
+                    self.actions,
+                );
+
+                if ui.put(button_rect, location_button).secondary_clicked() {
+                    self.actions.push(Action::ToggleLocationCleared {
+                        location: location.id.clone(),
+                    });
+                }
+            }
+        }
+    }
+}
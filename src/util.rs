@@ -90,6 +90,26 @@ where
     }
 }
 
+pub(crate) fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    #[derive(Serialize, Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany<T> {
+        One(T),
+        Many(Vec<T>),
+    }
+
+    let one_or_many = OneOrMany::deserialize(deserializer)?;
+
+    match one_or_many {
+        OneOrMany::One(value) => Ok(vec![value]),
+        OneOrMany::Many(values) => Ok(values),
+    }
+}
+
 pub const fn const_bool<const C: bool>() -> bool {
     C
 }
@@ -0,0 +1,50 @@
+Tracker:AddItems("items/items.json")
+
+slot_data = nil
+received_items = {}
+checked_locations = {}
+locations_on_clear = nil
+retrieved = {}
+set_replies = {}
+
+function on_clear(data)
+    slot_data = data
+    received_items = {}
+    checked_locations = {}
+    locations_on_clear = { #Archipelago.CheckedLocations, #Archipelago.MissingLocations }
+
+    for _, code in ipairs({ "sword", "lamp" }) do
+        Tracker:FindObjectForCode(code).Active = false
+    end
+
+    Archipelago:SetNotify({ "area" })
+    Archipelago:Get({ "area" })
+end
+
+function on_item(index, item_id, item_name, player_number)
+    table.insert(received_items, item_name)
+
+    local item = Tracker:FindObjectForCode(string.lower(item_name))
+
+    if item then
+        item.Active = true
+    end
+end
+
+function on_location(location_id, location_name)
+    table.insert(checked_locations, location_name)
+end
+
+function on_retrieved(key, value)
+    retrieved[key] = value
+end
+
+function on_set_reply(key, value, old_value)
+    set_replies[key] = value
+end
+
+Archipelago:AddClearHandler("clear", on_clear)
+Archipelago:AddItemHandler("item", on_item)
+Archipelago:AddLocationHandler("location", on_location)
+Archipelago:AddRetrievedHandler("retrieved", on_retrieved)
+Archipelago:AddSetReplyHandler("set_reply", on_set_reply)
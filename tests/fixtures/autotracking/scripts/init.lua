@@ -0,0 +1,21 @@
+sword = 0
+shield = 0
+
+function update_equipment(segment)
+    sword = segment:ReadU8(0x7ef340)
+    shield = segment:ReadU16(0x7ef340)
+end
+
+ScriptHost:AddMemoryWatch("Equipment", 0x7ef340, 2, update_equipment, 10)
+
+uat_sword = 0
+uat_shield = 0
+uat_changed = {}
+
+function update_variables(store, changed)
+    uat_sword = store:ReadVariable("sword") or 0
+    uat_shield = store:ReadVariable("shield") or 0
+    uat_changed = changed
+end
+
+ScriptHost:AddVariableWatch("Variables", {"sword", "shield"}, update_variables)
@@ -0,0 +1,44 @@
+-- Lantern that is first found and then lit
+lantern = ScriptHost:CreateLuaItem()
+lantern.Name = "Lantern"
+lantern.Icon = "images/lantern_0.png"
+lantern.ItemState = { stage = 0 }
+
+function set_lantern_stage(self, stage)
+    self.ItemState.stage = math.max(0, math.min(stage, 2))
+    self.Icon = "images/lantern_" .. self.ItemState.stage .. ".png"
+end
+
+lantern.OnLeftClickFunc = function(self)
+    set_lantern_stage(self, self.ItemState.stage + 1)
+end
+
+lantern.OnRightClickFunc = function(self)
+    set_lantern_stage(self, self.ItemState.stage - 1)
+end
+
+lantern.CanProvideCodeFunc = function(self, code)
+    return code == "lantern" or code == "lit_lantern"
+end
+
+lantern.ProvidesCodeFunc = function(self, code)
+    if code == "lantern" and self.ItemState.stage >= 1 then
+        return 1
+    end
+
+    if code == "lit_lantern" and self.ItemState.stage >= 2 then
+        return 1
+    end
+
+    return 0
+end
+
+lantern.SaveFunc = function(self)
+    return { stage = self.ItemState.stage }
+end
+
+lantern.LoadFunc = function(self, data)
+    set_lantern_stage(self, data.stage)
+end
+
+Tracker:AddLocations("locations/locations.json")
@@ -0,0 +1,19 @@
+Tracker:AddItems("items/items.json")
+Tracker:AddLocations("locations/locations.json")
+
+code_calls = {}
+section_calls = {}
+section_chests = {}
+
+function record_code(code)
+    table.insert(code_calls, code)
+end
+
+function record_section(section)
+    table.insert(section_calls, section.FullID)
+    table.insert(section_chests, section.AvailableChestCount)
+end
+
+ScriptHost:AddWatchForCode("sword", "sword", record_code)
+ScriptHost:AddWatchForCode("all", "*", record_code)
+ScriptHost:AddOnLocationSectionChangedHandler("sections", record_section)